@@ -32,6 +32,7 @@ fn main() {
             let ray = Ray {
                 origin: origin,
                 direction: lower_left_corner + horizontal * u + vertical * v - origin,
+                time: 0.0,
             };
             write_color_03_3(&output, ray.color_04_2());
         }
@@ -34,6 +34,7 @@ fn main() {
             let ray = Ray {
                 origin: origin,
                 direction: direction,
+                time: 0.0,
             };
             write_color(&output, ray.color_06_1(), 0, false);
         }
@@ -0,0 +1,129 @@
+// a pixel's mean luminance is trusted down to this, so black pixels don't need
+// an exact zero to stop
+const DARKEST: f64 = 1e-3;
+
+// sampling that stops on a pixel once its estimate is good enough, spending the
+// samples on noisy edges, shadows and caustics rather than flat sky. the
+// render's samples_per_pixel is the most a pixel takes
+#[derive(Copy, Clone, Debug)]
+pub struct AdaptiveSampling {
+    pub min_samples_per_pixel: i32,
+    // largest standard error of a pixel's mean luminance, relative to the mean,
+    // that it stops at
+    pub noise_threshold: f64,
+}
+
+impl AdaptiveSampling {
+    pub fn new(min_samples_per_pixel: i32, noise_threshold: f64) -> AdaptiveSampling {
+        AdaptiveSampling {
+            min_samples_per_pixel,
+            noise_threshold,
+        }
+    }
+
+    // whether the pixel has enough samples. the minimum guards against stopping
+    // before a rare bright path has had a chance to show up
+    pub fn converged(&self, luminance: &RunningVariance) -> bool {
+        if luminance.count < self.min_samples_per_pixel.max(2) as usize {
+            return false;
+        }
+        luminance.standard_error() <= self.noise_threshold * luminance.mean.max(DARKEST)
+    }
+}
+
+// mean and variance of a stream of values, updated one at a time (welford, 1962)
+#[derive(Copy, Clone, Debug)]
+pub struct RunningVariance {
+    pub count: usize,
+    pub mean: f64,
+    m2: f64,
+}
+
+impl RunningVariance {
+    pub fn new() -> RunningVariance {
+        RunningVariance {
+            count: 0,
+            mean: 0.0,
+            m2: 0.0,
+        }
+    }
+
+    pub fn add(&mut self, value: f64) {
+        self.count += 1;
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (value - self.mean);
+    }
+
+    // sample variance of the values
+    pub fn variance(&self) -> f64 {
+        if self.count < 2 {
+            0.0
+        } else {
+            self.m2 / (self.count - 1) as f64
+        }
+    }
+
+    // how far the mean is likely to be from the true mean
+    pub fn standard_error(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            (self.variance() / self.count as f64).sqrt()
+        }
+    }
+}
+
+impl Default for RunningVariance {
+    fn default() -> RunningVariance {
+        RunningVariance::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn running_variance_matches_two_passes() {
+        // a large offset is where the textbook one-pass formula loses digits
+        let values: std::vec::Vec<f64> = (0..1000)
+            .map(|i| 1e6 + ((i * 7919) % 101) as f64 * 0.01)
+            .collect();
+        let mut running = RunningVariance::new();
+        for value in values.iter() {
+            running.add(*value);
+        }
+
+        let n = values.len() as f64;
+        let mean = values.iter().sum::<f64>() / n;
+        let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0);
+
+        assert_eq!(running.count, values.len());
+        assert!((running.mean - mean).abs() < 1e-9);
+        assert!((running.variance() - variance).abs() < 1e-9 * variance);
+        assert!((running.standard_error() - (variance / n).sqrt()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn too_few_values_have_no_variance() {
+        let mut running = RunningVariance::new();
+        assert_eq!(running.standard_error(), 0.0);
+        running.add(3.0);
+        assert_eq!((running.mean, running.variance()), (3.0, 0.0));
+    }
+
+    #[test]
+    fn converges_only_past_the_minimum() {
+        let adaptive = AdaptiveSampling::new(8, 0.01);
+        let mut running = RunningVariance::new();
+        for _ in 0..7 {
+            running.add(0.5);
+        }
+        assert!(!adaptive.converged(&running));
+        running.add(0.5);
+        assert!(adaptive.converged(&running));
+        running.add(5.0);
+        assert!(!adaptive.converged(&running));
+    }
+}
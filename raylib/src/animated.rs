@@ -0,0 +1,132 @@
+use crate::hittable::{Hit, Hittable};
+use crate::material::Material;
+use crate::quaternion::Quaternion;
+use crate::ray::Ray;
+use crate::vec::Vec3;
+
+// where an object is at one moment, time in seconds. it's scaled uniformly
+// about its origin, then rotated and then moved by the translation
+#[derive(Copy, Clone, Debug)]
+pub struct TransformKey {
+    pub time: f64,
+    pub translation: Vec3,
+    pub rotation: Quaternion,
+    pub scale: f64,
+}
+
+impl TransformKey {
+    pub fn new(time: f64, translation: Vec3, rotation: Quaternion, scale: f64) -> TransformKey {
+        TransformKey {
+            time,
+            translation,
+            rotation,
+            scale,
+        }
+    }
+
+    fn to_world(self, p: Vec3) -> Vec3 {
+        self.rotation.rotate(p * self.scale) + self.translation
+    }
+}
+
+// an object moving through keyframed transforms, holding still before the first
+// key and after the last. rays meet it where it is at their time, so it blurs
+// over the camera's shutter, rotations included. translation and scale are
+// interpolated linearly and rotations with slerp. it isn't sampled as a light,
+// so moving emitters are only found by paths running into them
+pub struct Animated {
+    object: Box<dyn Hittable>,
+    keys: std::vec::Vec<TransformKey>,
+}
+
+impl Animated {
+    pub fn new(object: Box<dyn Hittable>, keys: std::vec::Vec<TransformKey>) -> Animated {
+        if keys.is_empty() {
+            panic!("error animated object has no keys");
+        }
+        if let Some(key) = keys
+            .iter()
+            .find(|key| key.scale <= 0.0 || key.scale.is_nan())
+        {
+            panic!("error animated object has scale {}", key.scale);
+        }
+        let mut keys = keys;
+        keys.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+        Animated { object, keys }
+    }
+
+    pub fn transform(&self, time: f64) -> TransformKey {
+        let keys = &self.keys;
+        let last = keys.len() - 1;
+        if time <= keys[0].time {
+            return keys[0];
+        }
+        if time >= keys[last].time {
+            return keys[last];
+        }
+
+        let i = keys.iter().rposition(|key| key.time <= time).unwrap_or(0);
+        let (a, b) = (&keys[i], &keys[i + 1]);
+        let h = b.time - a.time;
+        let u = if h > 0.0 { (time - a.time) / h } else { 0.0 };
+
+        TransformKey {
+            time,
+            translation: a.translation * (1.0 - u) + b.translation * u,
+            rotation: Quaternion::slerp(&a.rotation, &b.rotation, u),
+            scale: a.scale * (1.0 - u) + b.scale * u,
+        }
+    }
+}
+
+impl Hittable for Animated {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Hit<'_>> {
+        // into the object's own space, where the ray keeps its parameter t
+        let key = self.transform(ray.time);
+        let inverse = key.rotation.conjugate();
+        let local = Ray::with_time(
+            inverse.rotate(ray.origin - key.translation) / key.scale,
+            inverse.rotate(ray.direction) / key.scale,
+            ray.time,
+        );
+
+        let mut hit = self.object.hit(&local, t_min, t_max)?;
+        hit.point = key.to_world(hit.point);
+        hit.normal = key.rotation.rotate(hit.normal);
+        hit.geometric_normal = key.rotation.rotate(hit.geometric_normal);
+        hit.dpdu = key.rotation.rotate(hit.dpdu) * key.scale;
+        hit.dpdv = key.rotation.rotate(hit.dpdv) * key.scale;
+        Some(hit)
+    }
+
+    // the object's box turned every way at every key, which covers where it goes
+    // in between since translation and scale move linearly
+    fn bounding_box(&self) -> Option<(Vec3, Vec3)> {
+        let (min, max) = self.object.bounding_box()?;
+        let reach = Vec3::new(
+            min.x.abs().max(max.x.abs()),
+            min.y.abs().max(max.y.abs()),
+            min.z.abs().max(max.z.abs()),
+        )
+        .length();
+
+        let mut bounds: Option<(Vec3, Vec3)> = None;
+        for key in self.keys.iter() {
+            let r = Vec3::new(1.0, 1.0, 1.0) * (reach * key.scale);
+            let (lo, hi) = (key.translation - r, key.translation + r);
+            bounds = Some(match bounds {
+                Some((a, b)) => (
+                    Vec3::new(a.x.min(lo.x), a.y.min(lo.y), a.z.min(lo.z)),
+                    Vec3::new(b.x.max(hi.x), b.y.max(hi.y), b.z.max(hi.z)),
+                ),
+                None => (lo, hi),
+            });
+        }
+        bounds
+    }
+
+    fn material(&self) -> Option<&dyn Material> {
+        self.object.material()
+    }
+}
@@ -0,0 +1,206 @@
+use crate::background::Background;
+use crate::camera::Camera;
+use crate::camera_builder::{CameraBuilder, CameraError};
+use crate::hittable::Hittables;
+use crate::render::{render, RenderSettings};
+use crate::vec::Vec3;
+
+// what the camera does at one moment, time in seconds
+#[derive(Copy, Clone, Debug)]
+pub struct CameraKey {
+    pub time: f64,
+    pub look_from: Vec3,
+    pub look_at: Vec3,
+    // vertical, in degrees
+    pub vfov: f64,
+    pub focus_distance: f64,
+}
+
+impl CameraKey {
+    pub fn new(
+        time: f64,
+        look_from: Vec3,
+        look_at: Vec3,
+        vfov: f64,
+        focus_distance: f64,
+    ) -> CameraKey {
+        CameraKey {
+            time,
+            look_from,
+            look_at,
+            vfov,
+            focus_distance,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum Interpolation {
+    // straight from key to key, with sharp turns at the keys
+    Linear,
+    // smooth curves through every key, with tangents from the neighbouring keys
+    // so uneven spacing in time doesn't overshoot
+    CatmullRom,
+}
+
+// a camera moving through keyframes, holding still before the first and after
+// the last
+pub struct CameraPath {
+    keys: std::vec::Vec<CameraKey>,
+    pub interpolation: Interpolation,
+    pub view_up: Vec3,
+    pub aspect_ratio: f64,
+    pub aperture: f64,
+}
+
+// hermite curve from p0 to p1 over a span of h with the tangents m0 and m1, at
+// the fraction u of the way
+fn hermite(p0: Vec3, m0: Vec3, p1: Vec3, m1: Vec3, h: f64, u: f64) -> Vec3 {
+    let u2 = u * u;
+    let u3 = u2 * u;
+    p0 * (2.0 * u3 - 3.0 * u2 + 1.0)
+        + m0 * (h * (u3 - 2.0 * u2 + u))
+        + p1 * (-2.0 * u3 + 3.0 * u2)
+        + m1 * (h * (u3 - u2))
+}
+
+impl CameraPath {
+    pub fn new(
+        keys: std::vec::Vec<CameraKey>,
+        interpolation: Interpolation,
+        view_up: Vec3,
+        aspect_ratio: f64,
+        aperture: f64,
+    ) -> CameraPath {
+        if keys.is_empty() {
+            panic!("error camera path has no keys");
+        }
+        let mut keys = keys;
+        keys.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+        CameraPath {
+            keys,
+            interpolation,
+            view_up,
+            aspect_ratio,
+            aperture,
+        }
+    }
+
+    // the camera's settings at time
+    pub fn key(&self, time: f64) -> CameraKey {
+        let keys = &self.keys;
+        let last = keys.len() - 1;
+        if time <= keys[0].time {
+            return CameraKey { time, ..keys[0] };
+        }
+        if time >= keys[last].time {
+            return CameraKey { time, ..keys[last] };
+        }
+
+        let i = keys.iter().rposition(|key| key.time <= time).unwrap_or(0);
+        let (a, b) = (&keys[i], &keys[i + 1]);
+        let h = b.time - a.time;
+        let u = if h > 0.0 { (time - a.time) / h } else { 0.0 };
+
+        // one value through the curve, scalars riding along in a vector
+        let curve = |value: &dyn Fn(&CameraKey) -> Vec3| match self.interpolation {
+            Interpolation::Linear => value(a) * (1.0 - u) + value(b) * u,
+            Interpolation::CatmullRom => {
+                // tangent at a key from the keys either side, one sided at the ends
+                let tangent = |j: usize| {
+                    let before = j.saturating_sub(1);
+                    let after = (j + 1).min(last);
+                    let dt = keys[after].time - keys[before].time;
+                    if dt > 0.0 {
+                        (value(&keys[after]) - value(&keys[before])) / dt
+                    } else {
+                        Vec3::new(0.0, 0.0, 0.0)
+                    }
+                };
+                hermite(value(a), tangent(i), value(b), tangent(i + 1), h, u)
+            }
+        };
+        let lens = curve(&|key| Vec3::new(key.vfov, key.focus_distance, 0.0));
+
+        CameraKey {
+            time,
+            look_from: curve(&|key| key.look_from),
+            look_at: curve(&|key| key.look_at),
+            vfov: lens.x,
+            focus_distance: lens.y,
+        }
+    }
+
+    // the camera at time, with its shutter opening and closing then. the
+    // builder checks the settings, which keys or their interpolation may break
+    pub fn camera(&self, time: f64) -> Result<Camera, CameraError> {
+        let key = self.key(time);
+        let mut camera = CameraBuilder::new()
+            .look_from(key.look_from)
+            .look_at(key.look_at)
+            .view_up(self.view_up)
+            .vfov(key.vfov)
+            .aspect_ratio(self.aspect_ratio)
+            .aperture(self.aperture)
+            .focus_distance(key.focus_distance)
+            .shutter_open(time)
+            .build()?;
+        camera.shutter_close = time;
+        Ok(camera)
+    }
+}
+
+// frames first to last, both included, with frame n at n / frames_per_second
+// seconds. the shutter opens at the frame's time and stays open for the share
+// of the frame the shutter angle gives, 180 degrees for half of it like film
+// cameras, blurring animated objects. 0 renders each frame at an instant
+#[derive(Copy, Clone, Debug)]
+pub struct FrameRange {
+    pub first: usize,
+    pub last: usize,
+    pub frames_per_second: f64,
+    // in degrees, 360 being the whole frame
+    pub shutter_angle: f64,
+}
+
+impl FrameRange {
+    pub fn new(first: usize, last: usize, frames_per_second: f64) -> FrameRange {
+        FrameRange {
+            first,
+            last,
+            frames_per_second,
+            shutter_angle: 180.0,
+        }
+    }
+
+    pub fn time(&self, frame: usize) -> f64 {
+        frame as f64 / self.frames_per_second
+    }
+
+    // how long the shutter stays open each frame, in seconds
+    pub fn exposure_time(&self) -> f64 {
+        self.shutter_angle.clamp(0.0, 360.0) / 360.0 / self.frames_per_second
+    }
+}
+
+// renders every frame of the range into directory as frame_0001.png and on. the
+// world is built once and only the camera changes from frame to frame. stops at
+// the first frame whose camera settings don't work
+pub fn render_frames(
+    path: &CameraPath,
+    world: &Hittables,
+    background: &dyn Background,
+    settings: &RenderSettings,
+    frames: &FrameRange,
+    directory: &str,
+    rng: &mut rand::rngs::ThreadRng,
+) -> Result<(), CameraError> {
+    for frame in frames.first..=frames.last {
+        let mut camera = path.camera(frames.time(frame))?;
+        camera.shutter_close = camera.shutter_open + frames.exposure_time();
+        let image = render(&camera, world, background, settings, rng);
+        image.write_png(&format!("{}/frame_{:04}.png", directory, frame));
+    }
+    Ok(())
+}
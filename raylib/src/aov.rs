@@ -0,0 +1,109 @@
+use crate::hittable::{Hit, Hittables};
+use crate::integrator::{next_surface, occluded, same_material};
+use crate::onb::Onb;
+use crate::random;
+use crate::ray::Ray;
+use crate::vec::Vec3;
+
+use std::f64::consts::PI;
+
+// arbitrary output variables: what the camera ray sees at its first surface,
+// for debugging scenes and feeding denoisers. rays that miss give black
+#[derive(Copy, Clone, Debug)]
+pub enum Aov {
+    // normals are mapped from [-1, 1] to [0, 1] like color_06_7
+    ShadingNormal,
+    GeometricNormal,
+    // distance from the camera, the same in all three channels
+    Depth,
+    // the share of light the surface reflects, estimated from one scattered ray
+    Albedo,
+    // u in red, v in green
+    Uv,
+    // a color per material or object, the same on every render
+    MaterialId,
+    ObjectId,
+    // whether a cosine distributed ray gets further than distance
+    AmbientOcclusion { distance: f64 },
+}
+
+impl Aov {
+    // for file names, beauty.ppm gets its layers next to it as beauty.<name>.ppm
+    pub fn name(&self) -> &'static str {
+        match self {
+            Aov::ShadingNormal => "normal",
+            Aov::GeometricNormal => "geometric_normal",
+            Aov::Depth => "depth",
+            Aov::Albedo => "albedo",
+            Aov::Uv => "uv",
+            Aov::MaterialId => "material_id",
+            Aov::ObjectId => "object_id",
+            Aov::AmbientOcclusion { .. } => "ao",
+        }
+    }
+
+    pub fn value(&self, ray: &Ray, world: &Hittables, rng: &mut rand::rngs::ThreadRng) -> Vec3 {
+        let black = Vec3::new(0.0, 0.0, 0.0);
+        let hit = match next_surface(world, ray, rng) {
+            Some(hit) => hit,
+            None => return black,
+        };
+        let one = Vec3::new(1.0, 1.0, 1.0);
+
+        match self {
+            Aov::ShadingNormal => (hit.normal + one) * 0.5,
+            Aov::GeometricNormal => (hit.geometric_normal + one) * 0.5,
+            Aov::Depth => one * (hit.t * ray.direction.length()),
+            Aov::Albedo => match hit.material {
+                Some(material) => match material.scatter(ray, hit, rng) {
+                    Some((_, attenuation)) => attenuation,
+                    None => black,
+                },
+                None => black,
+            },
+            Aov::Uv => Vec3::new(hit.u, hit.v, 0.0),
+            Aov::MaterialId => match material_id(world, &hit) {
+                Some(id) => id_color(id),
+                None => black,
+            },
+            Aov::ObjectId => id_color(hit.object),
+            Aov::AmbientOcclusion { distance } => {
+                let r1 = random(rng);
+                let r2 = random(rng);
+                let phi = 2.0 * PI * r1;
+                let direction = Onb::from_w(hit.normal).local(
+                    phi.cos() * r2.sqrt(),
+                    phi.sin() * r2.sqrt(),
+                    (1.0 - r2).sqrt(),
+                );
+                let ray = Ray::with_time(hit.point, direction * *distance, ray.time);
+                if occluded(world, &ray, 1.0, rng) {
+                    black
+                } else {
+                    one
+                }
+            }
+        }
+    }
+}
+
+// index of the first object in the world with the hit's material
+fn material_id(world: &Hittables, hit: &Hit) -> Option<usize> {
+    let material = hit.material?;
+    world.list.iter().position(|object| {
+        object
+            .material()
+            .is_some_and(|other| same_material(other, material))
+    })
+}
+
+// ids spread around the hue circle by the golden ratio so neighbours differ,
+// fully saturated
+fn id_color(id: usize) -> Vec3 {
+    let hue = ((id as f64 + 1.0) * 0.618_033_988_749_895).fract() * 6.0;
+    let channel = |offset: f64| {
+        let h = (hue + offset) % 6.0;
+        ((h - 3.0).abs() - 1.0).clamp(0.0, 1.0)
+    };
+    Vec3::new(channel(0.0), channel(4.0), channel(2.0))
+}
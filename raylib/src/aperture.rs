@@ -0,0 +1,140 @@
+use crate::image::Image;
+use crate::{concentric_disk, random, random_in_unit_disk};
+
+use std::f64::consts::PI;
+
+// shape of the lens opening, which out of focus highlights take on. points are
+// on the scale of the unit disk, which the camera's lens radius then scales
+#[derive(Clone)]
+pub enum Aperture {
+    Circle,
+    // regular polygon from straight blades, rotation in degrees
+    Polygon { blades: usize, rotation: f64 },
+    // the image's luminance is how much light gets through where, stretched over
+    // the square around the unit disk. transmission is the average over it
+    Mask { image: Image, transmission: f64 },
+}
+
+impl Aperture {
+    pub fn polygon(blades: usize, rotation: f64) -> Aperture {
+        if blades < 3 {
+            panic!("error aperture needs at least 3 blades");
+        }
+        Aperture::Polygon {
+            blades,
+            rotation: rotation.to_radians(),
+        }
+    }
+
+    pub fn mask(image: Image) -> Aperture {
+        if image.width == 0 || image.height == 0 || image.pixels.is_empty() {
+            panic!("error aperture mask is empty");
+        }
+        let total: f64 = image
+            .pixels
+            .iter()
+            .map(|p| p.luminance().clamp(0.0, 1.0))
+            .sum();
+        let transmission = total / image.pixels.len() as f64;
+        if transmission <= 0.0 {
+            panic!("error aperture mask lets no light through");
+        }
+
+        Aperture::Mask {
+            image,
+            transmission,
+        }
+    }
+
+    // uniformly distributed over the opening, weighted by how much the mask lets through
+    pub fn sample(&self, rng: &mut rand::rngs::ThreadRng) -> (f64, f64) {
+        match self {
+            Aperture::Circle => {
+                let p = random_in_unit_disk(rng);
+                (p.x, p.y)
+            }
+            Aperture::Polygon { blades, rotation } => {
+                // one of the equal triangles between the center and two corners
+                let step = 2.0 * PI / *blades as f64;
+                let i = ((random(rng) * *blades as f64) as usize).min(blades - 1);
+                let a = rotation + step * i as f64;
+                let b = a + step;
+
+                let mut r1 = random(rng);
+                let mut r2 = random(rng);
+                if r1 + r2 > 1.0 {
+                    r1 = 1.0 - r1;
+                    r2 = 1.0 - r2;
+                }
+                (r1 * a.cos() + r2 * b.cos(), r1 * a.sin() + r2 * b.sin())
+            }
+            Aperture::Mask {
+                image,
+                transmission,
+            } => {
+                if *transmission <= 0.0 {
+                    return (0.0, 0.0);
+                }
+                // rejection sampling, the mask being mostly open or closed
+                for _ in 0..256 {
+                    let x = random(rng);
+                    let y = random(rng);
+                    let px = ((x * image.width as f64) as usize).min(image.width - 1);
+                    let py = (((1.0 - y) * image.height as f64) as usize).min(image.height - 1);
+                    if random(rng) < image.get(px, py).luminance() {
+                        return (2.0 * x - 1.0, 2.0 * y - 1.0);
+                    }
+                }
+                (0.0, 0.0)
+            }
+        }
+    }
+
+    // like sample, with the point on the opening picked by u from the unit square
+    // so well spread u give well spread points. the mask still draws its own
+    // numbers past the first try
+    pub fn sample_point(&self, u: (f64, f64), rng: &mut rand::rngs::ThreadRng) -> (f64, f64) {
+        match self {
+            Aperture::Circle => {
+                let p = concentric_disk(u.0, u.1);
+                (p.x, p.y)
+            }
+            Aperture::Polygon { blades, rotation } => {
+                // the first number picks the triangle and is reused within it
+                let step = 2.0 * PI / *blades as f64;
+                let scaled = u.0 * *blades as f64;
+                let i = (scaled as usize).min(blades - 1);
+                let a = rotation + step * i as f64;
+                let b = a + step;
+
+                let mut r1 = (scaled - i as f64).clamp(0.0, 1.0);
+                let mut r2 = u.1;
+                if r1 + r2 > 1.0 {
+                    r1 = 1.0 - r1;
+                    r2 = 1.0 - r2;
+                }
+                (r1 * a.cos() + r2 * b.cos(), r1 * a.sin() + r2 * b.sin())
+            }
+            Aperture::Mask { image, .. } => {
+                let px = ((u.0 * image.width as f64) as usize).min(image.width - 1);
+                let py = (((1.0 - u.1) * image.height as f64) as usize).min(image.height - 1);
+                if random(rng) < image.get(px, py).luminance() {
+                    return (2.0 * u.0 - 1.0, 2.0 * u.1 - 1.0);
+                }
+                self.sample(rng)
+            }
+        }
+    }
+
+    // area of the opening on the unit disk's scale
+    pub fn area(&self) -> f64 {
+        match self {
+            Aperture::Circle => PI,
+            Aperture::Polygon { blades, .. } => {
+                let n = *blades as f64;
+                n / 2.0 * (2.0 * PI / n).sin()
+            }
+            Aperture::Mask { transmission, .. } => 4.0 * transmission,
+        }
+    }
+}
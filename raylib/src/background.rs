@@ -0,0 +1,156 @@
+use crate::distribution::Distribution2D;
+use crate::hdr::Hdr;
+use crate::uniform_sphere;
+use crate::vec::Vec3;
+
+use std::f64::consts::PI;
+
+// radiance arriving from infinitely far away, seen by rays that miss the world
+pub trait Background {
+    fn color(&self, direction: Vec3) -> Vec3;
+
+    // picks a direction towards the background from the point u of the unit
+    // square, returning (direction, radiance, pdf) with the pdf measured in
+    // solid angle
+    fn sample(&self, u: (f64, f64)) -> Option<(Vec3, Vec3, f64)> {
+        let direction = uniform_sphere(u.0, u.1);
+        Some((direction, self.color(direction), 1.0 / (4.0 * PI)))
+    }
+
+    fn pdf(&self, _direction: Vec3) -> f64 {
+        1.0 / (4.0 * PI)
+    }
+}
+
+pub struct Constant {
+    pub color: Vec3,
+}
+
+impl Constant {
+    pub fn new(color: Vec3) -> Constant {
+        Constant { color }
+    }
+}
+
+impl Background for Constant {
+    fn color(&self, _direction: Vec3) -> Vec3 {
+        self.color
+    }
+}
+
+// blends from `bottom` straight down to `top` straight up
+pub struct Gradient {
+    pub bottom: Vec3,
+    pub top: Vec3,
+}
+
+impl Gradient {
+    pub fn new(bottom: Vec3, top: Vec3) -> Gradient {
+        Gradient { bottom, top }
+    }
+
+    // the white to blue sky of Ray::color_04_2
+    pub fn sky() -> Gradient {
+        Gradient::new(Vec3::new(1.0, 1.0, 1.0), Vec3::new(0.5, 0.7, 1.0))
+    }
+}
+
+impl Background for Gradient {
+    fn color(&self, direction: Vec3) -> Vec3 {
+        let t = 0.5 * (direction.unit().y + 1.0);
+
+        self.bottom * (1.0 - t) + self.top * t
+    }
+}
+
+// equirectangular (latitude/longitude) image: the center of the image looks down -z,
+// the top row looks straight up
+pub struct EnvironmentMap {
+    pub image: Hdr,
+    pub intensity: f64,
+    pub distribution: Distribution2D,
+}
+
+// maps a direction onto [0, 1)^2 image coordinates
+pub fn direction_to_uv(direction: Vec3) -> (f64, f64) {
+    let d = direction.unit();
+    let theta = d.y.clamp(-1.0, 1.0).acos();
+    let phi = d.x.atan2(-d.z);
+
+    (0.5 + phi / (2.0 * PI), theta / PI)
+}
+
+pub fn uv_to_direction(u: f64, v: f64) -> Vec3 {
+    let phi = (u - 0.5) * 2.0 * PI;
+    let theta = v * PI;
+
+    Vec3::new(
+        theta.sin() * phi.sin(),
+        theta.cos(),
+        -theta.sin() * phi.cos(),
+    )
+}
+
+impl EnvironmentMap {
+    pub fn new(image: Hdr, intensity: f64) -> EnvironmentMap {
+        // weight each texel by the solid angle it covers, rows near the poles are squashed
+        let mut func = std::vec::Vec::with_capacity(image.width * image.height);
+        for y in 0..image.height {
+            let sin_theta = (PI * (y as f64 + 0.5) / image.height as f64).sin();
+            for x in 0..image.width {
+                func.push(image.pixels[y * image.width + x].luminance() * sin_theta);
+            }
+        }
+        let distribution = Distribution2D::new(&func, image.width, image.height);
+
+        EnvironmentMap {
+            image,
+            intensity,
+            distribution,
+        }
+    }
+
+    pub fn open(path: &str, intensity: f64) -> EnvironmentMap {
+        EnvironmentMap::new(Hdr::open(path), intensity)
+    }
+
+    fn lookup(&self, u: f64, v: f64) -> Vec3 {
+        let x = ((u * self.image.width as f64) as usize).min(self.image.width - 1);
+        let y = ((v * self.image.height as f64) as usize).min(self.image.height - 1);
+
+        self.image.pixels[y * self.image.width + x] * self.intensity
+    }
+}
+
+impl Background for EnvironmentMap {
+    fn color(&self, direction: Vec3) -> Vec3 {
+        let (u, v) = direction_to_uv(direction);
+        self.lookup(u, v)
+    }
+
+    fn sample(&self, u: (f64, f64)) -> Option<(Vec3, Vec3, f64)> {
+        let (u, v, pdf_uv) = self.distribution.sample_continuous(u.0, u.1);
+        if pdf_uv == 0.0 {
+            return None;
+        }
+        let sin_theta = (v * PI).sin();
+        if sin_theta == 0.0 {
+            return None;
+        }
+
+        // change of variables from the image to the sphere of directions
+        let pdf = pdf_uv / (2.0 * PI * PI * sin_theta);
+
+        Some((uv_to_direction(u, v), self.lookup(u, v), pdf))
+    }
+
+    fn pdf(&self, direction: Vec3) -> f64 {
+        let (u, v) = direction_to_uv(direction);
+        let sin_theta = (v * PI).sin();
+        if sin_theta == 0.0 {
+            return 0.0;
+        }
+
+        self.distribution.pdf(u, v) / (2.0 * PI * PI * sin_theta)
+    }
+}
@@ -0,0 +1,684 @@
+use crate::background::Background;
+use crate::camera::Camera;
+use crate::hittable::{Hit, Hittables};
+use crate::integrator::{next_surface, occluded, SURFACE_EPSILON};
+use crate::lights::Lights;
+use crate::onb::Onb;
+use crate::random;
+use crate::ray::Ray;
+use crate::render::RenderSettings;
+use crate::vec::Vec3;
+
+use std::f64::consts::PI;
+
+#[derive(Copy, Clone, PartialEq)]
+enum Kind {
+    Camera,
+    Light,
+    Background,
+    Surface,
+}
+
+// one vertex of a camera or light subpath. pdfs are per unit area, apart from
+// background vertices which sit infinitely far away and use solid angle
+#[derive(Copy, Clone)]
+struct Vertex<'world> {
+    kind: Kind,
+    // position, or the direction towards the background for background vertices
+    point: Vec3,
+    // geometric normal facing where the path came from, zero off surfaces
+    normal: Vec3,
+    // unit direction back towards the previous vertex
+    wo: Vec3,
+    hit: Option<Hit<'world>>,
+    beta: Vec3,
+    // pdf of this vertex being sampled from the previous one along the subpath,
+    // and from the next one by a path built the other way round
+    pdf_fwd: f64,
+    pdf_rev: f64,
+    // scattered by a specular material, so it can't be connected to
+    delta: bool,
+}
+
+impl<'world> Vertex<'world> {
+    fn new(kind: Kind, point: Vec3, normal: Vec3, beta: Vec3, pdf_fwd: f64) -> Vertex<'world> {
+        Vertex {
+            kind,
+            point,
+            normal,
+            wo: Vec3::new(0.0, 0.0, 0.0),
+            hit: None,
+            beta,
+            pdf_fwd,
+            pdf_rev: 0.0,
+            delta: false,
+        }
+    }
+
+    fn on_surface(&self) -> bool {
+        self.normal.length_squared() > 0.0
+    }
+
+    fn direction_to(&self, other: &Vertex) -> Vec3 {
+        if other.kind == Kind::Background {
+            other.point
+        } else {
+            (other.point - self.point).unit()
+        }
+    }
+}
+
+// turns a pdf per unit solid angle at `from` into one per unit area at `to`
+fn convert_density(pdf: f64, from: &Vertex, to: &Vertex) -> f64 {
+    if to.kind == Kind::Background {
+        return pdf;
+    }
+    let w = to.point - from.point;
+    let distance_squared = w.length_squared();
+    if distance_squared == 0.0 {
+        return 0.0;
+    }
+    let mut pdf = pdf / distance_squared;
+    if to.on_surface() {
+        pdf *= to.normal.dot(&w).abs() / distance_squared.sqrt();
+    }
+    pdf
+}
+
+// the hit with its normals facing `direction`, the side light arrives from
+fn oriented<'world>(hit: &Hit<'world>, direction: Vec3) -> Hit<'world> {
+    let mut hit = *hit;
+    if direction.dot(&hit.geometric_normal) < 0.0 {
+        hit.normal = -hit.normal;
+        hit.geometric_normal = -hit.geometric_normal;
+        hit.front_face = !hit.front_face;
+    }
+    hit
+}
+
+// the material's f * cos and pdf for light arriving from `from` and leaving
+// towards `to`, both unit directions pointing away from the hit
+fn eval(hit: &Hit, from: Vec3, to: Vec3) -> Option<(Vec3, f64)> {
+    let material = hit.material?;
+    let hit = oriented(hit, from);
+    let ray = Ray::new(hit.point + from, -from);
+    material.eval(&ray, &hit, to)
+}
+
+fn remap(pdf: f64) -> f64 {
+    if pdf != 0.0 {
+        pdf
+    } else {
+        1.0
+    }
+}
+
+// bidirectional path tracer (veach, 1997): every sample traces a subpath from
+// the camera and one from a light, connects each prefix of the one to each
+// prefix of the other, and weights the strategies with the balance heuristic.
+// lights are the emissive shapes of the world, picked by area, and the
+// background, which is only ever reached by the camera subpath or sampled
+// directly. paths that hit the image plane from the light side are splatted.
+// media are ignored, their boundaries are passed through as if empty
+pub struct Bidirectional<'world> {
+    camera: &'world Camera,
+    world: &'world Hittables,
+    background: &'world dyn Background,
+    lights: Lights<'world>,
+    max_depth: usize,
+    // how far s, t reach past 1 for the image's last row and column, and the
+    // share of the camera's importance the image gets
+    s_max: f64,
+    t_max: f64,
+    film_scale: f64,
+}
+
+impl<'world> Bidirectional<'world> {
+    pub fn new(
+        camera: &'world Camera,
+        world: &'world Hittables,
+        background: &'world dyn Background,
+        settings: &RenderSettings,
+        rng: &mut rand::rngs::ThreadRng,
+    ) -> Bidirectional<'world> {
+        let width = settings.width as f64;
+        let height = settings.height as f64;
+        let (span_x, span_y) = settings.film_span();
+        Bidirectional {
+            camera,
+            world,
+            background,
+            lights: Lights::new(world, rng),
+            max_depth: settings.max_depth.max(0) as usize,
+            s_max: width / span_x,
+            t_max: height / span_y,
+            film_scale: span_x * span_y / (width * height),
+        }
+    }
+
+    // radiance along the camera ray, and the light reaching the camera
+    // elsewhere on the image as (s, t, color) splats
+    pub fn trace(
+        &self,
+        ray: &Ray,
+        rng: &mut rand::rngs::ThreadRng,
+    ) -> (Vec3, std::vec::Vec<(f64, f64, Vec3)>) {
+        let camera_path = self.camera_subpath(ray, rng);
+        let light_path = self.light_subpath(ray.time, rng);
+
+        let mut color = Vec3::new(0.0, 0.0, 0.0);
+        let mut splats = std::vec::Vec::new();
+
+        // s = 1 samples a light of its own, so it is there even without a light path
+        let light_vertices = light_path.len().max(1);
+        for t in 1..=camera_path.len() {
+            for s in 0..=light_vertices {
+                let vertices = s + t;
+                if (s == 1 && t == 1) || vertices < 2 || vertices - 2 > self.max_depth {
+                    continue;
+                }
+                if t == 1 {
+                    if !self.camera.has_importance() {
+                        continue;
+                    }
+                    if let Some((s, t, splat)) = self.connect_camera(&light_path, s, ray.time, rng)
+                    {
+                        splats.push((s, t, splat));
+                    }
+                } else {
+                    color = color + self.connect(&camera_path, &light_path, s, t, ray.time, rng);
+                }
+            }
+        }
+        (color, splats)
+    }
+
+    fn camera_subpath(
+        &self,
+        ray: &Ray,
+        rng: &mut rand::rngs::ThreadRng,
+    ) -> std::vec::Vec<Vertex<'world>> {
+        let mut path = std::vec::Vec::new();
+        let direction = ray.direction.unit();
+        let zero = Vec3::new(0.0, 0.0, 0.0);
+        path.push(Vertex::new(
+            Kind::Camera,
+            ray.origin,
+            zero,
+            Vec3::new(1.0, 1.0, 1.0),
+            0.0,
+        ));
+
+        let (_, pdf_direction) = self.camera.pdf_importance(direction);
+        self.random_walk(
+            Ray::with_time(ray.origin, direction, ray.time),
+            Vec3::new(1.0, 1.0, 1.0),
+            pdf_direction,
+            self.max_depth + 2,
+            true,
+            &mut path,
+            rng,
+        );
+        path
+    }
+
+    // a path leaving a light at the same moment as the camera path
+    fn light_subpath(
+        &self,
+        time: f64,
+        rng: &mut rand::rngs::ThreadRng,
+    ) -> std::vec::Vec<Vertex<'world>> {
+        let mut path = std::vec::Vec::new();
+        let light = match self.lights.pick(rng) {
+            Some(light) => light,
+            None => return path,
+        };
+        let hit = match light.object.sample(rng) {
+            Some(hit) => hit,
+            None => return path,
+        };
+        let emitted = light.material.emitted(&hit);
+        let pdf_position = self.lights.pdf_position();
+        if emitted.near_zero() || pdf_position <= 0.0 {
+            return path;
+        }
+
+        let mut vertex = Vertex::new(
+            Kind::Light,
+            hit.point,
+            hit.geometric_normal,
+            emitted / pdf_position,
+            pdf_position,
+        );
+        vertex.hit = Some(hit);
+        path.push(vertex);
+
+        // cosine weighted around the normal, which cancels against the emission's
+        // cosine but for pi
+        let onb = Onb::from_w(hit.geometric_normal);
+        let r1 = random(rng);
+        let r2 = random(rng);
+        let phi = 2.0 * PI * r1;
+        let direction = onb.local(
+            phi.cos() * r2.sqrt(),
+            phi.sin() * r2.sqrt(),
+            (1.0 - r2).sqrt(),
+        );
+        let cos_theta = (1.0 - r2).sqrt();
+        if cos_theta <= 0.0 {
+            return path;
+        }
+
+        self.random_walk(
+            Ray::with_time(hit.point, direction, time),
+            vertex.beta * PI,
+            cos_theta / PI,
+            self.max_depth + 1,
+            false,
+            &mut path,
+            rng,
+        );
+        path
+    }
+
+    // extends the path by following the materials' scattered rays until it has
+    // max_vertices vertices. camera paths that escape end on a background vertex
+    #[allow(clippy::too_many_arguments)]
+    fn random_walk(
+        &self,
+        ray: Ray,
+        beta: Vec3,
+        pdf: f64,
+        max_vertices: usize,
+        camera_path: bool,
+        path: &mut std::vec::Vec<Vertex<'world>>,
+        rng: &mut rand::rngs::ThreadRng,
+    ) {
+        let mut ray = ray;
+        let mut beta = beta;
+        let mut pdf_fwd = pdf;
+
+        while path.len() < max_vertices && !beta.near_zero() {
+            let hit = match next_surface(self.world, &ray, rng) {
+                Some(hit) => hit,
+                None => {
+                    if camera_path {
+                        let zero = Vec3::new(0.0, 0.0, 0.0);
+                        let direction = ray.direction.unit();
+                        let mut vertex =
+                            Vertex::new(Kind::Background, direction, zero, beta, pdf_fwd);
+                        vertex.wo = -direction;
+                        path.push(vertex);
+                    }
+                    return;
+                }
+            };
+            let material = match hit.material {
+                Some(material) => material,
+                None => panic!("expecting material"),
+            };
+
+            let wo = -ray.direction.unit();
+            let mut vertex = Vertex::new(Kind::Surface, hit.point, hit.geometric_normal, beta, 0.0);
+            vertex.wo = wo;
+            vertex.hit = Some(hit);
+            vertex.pdf_fwd = convert_density(pdf_fwd, &path[path.len() - 1], &vertex);
+            path.push(vertex);
+            if path.len() >= max_vertices {
+                return;
+            }
+
+            let (scattered, attenuation, pdf) = match material.scatter_with_pdf(&ray, hit, rng) {
+                Some(scattered) => scattered,
+                None => return,
+            };
+            let direction = scattered.direction.unit();
+
+            // pdf of the walk having come the other way, through the same hit
+            let pdf_rev = match pdf {
+                Some(_) => eval(&hit, direction, wo).map_or(0.0, |(_, pdf)| pdf),
+                None => 0.0,
+            };
+            let n = path.len();
+            path[n - 1].delta = pdf.is_none();
+            let reverse = convert_density(pdf_rev, &path[n - 1], &path[n - 2]);
+            path[n - 2].pdf_rev = reverse;
+
+            beta = beta * attenuation;
+            pdf_fwd = pdf.unwrap_or(0.0);
+            ray = Ray::with_time(hit.point, direction, ray.time);
+        }
+    }
+
+    // f * cos at a surface vertex for light leaving towards `to`
+    fn f(&self, vertex: &Vertex, to: Vec3) -> Vec3 {
+        match vertex.hit {
+            Some(hit) => eval(&hit, vertex.wo, to).map_or(Vec3::new(0.0, 0.0, 0.0), |(f, _)| f),
+            None => Vec3::new(0.0, 0.0, 0.0),
+        }
+    }
+
+    // pdf per unit area of `next` being sampled from `vertex`, which was reached
+    // from `prev`
+    fn pdf(&self, vertex: &Vertex, prev: Option<&Vertex>, next: &Vertex) -> f64 {
+        match vertex.kind {
+            Kind::Light => self.pdf_light(vertex, next),
+            Kind::Background => 0.0,
+            Kind::Camera => {
+                let (_, pdf) = self.camera.pdf_importance(vertex.direction_to(next));
+                convert_density(pdf, vertex, next)
+            }
+            Kind::Surface => {
+                let prev = match prev {
+                    Some(prev) => prev,
+                    None => return 0.0,
+                };
+                let hit = match vertex.hit {
+                    Some(hit) => hit,
+                    None => return 0.0,
+                };
+                match eval(&hit, vertex.direction_to(prev), vertex.direction_to(next)) {
+                    Some((_, pdf)) => convert_density(pdf, vertex, next),
+                    None => 0.0,
+                }
+            }
+        }
+    }
+
+    // pdf per unit area of `next` being reached by a light subpath leaving the
+    // emitter at `vertex`
+    fn pdf_light(&self, vertex: &Vertex, next: &Vertex) -> f64 {
+        if vertex.kind == Kind::Background {
+            return 0.0;
+        }
+        let cos_theta = vertex.normal.dot(&vertex.direction_to(next));
+        if cos_theta <= 0.0 {
+            return 0.0;
+        }
+        convert_density(cos_theta / PI, vertex, next)
+    }
+
+    // pdf of a light subpath starting at `vertex`
+    fn pdf_light_origin(&self, vertex: &Vertex) -> f64 {
+        if vertex.kind == Kind::Background {
+            return self.lights.background_probability() * self.background.pdf(vertex.point);
+        }
+        self.lights.pdf_position()
+    }
+
+    // unoccluded apart from medium boundaries
+    fn visible(&self, from: Vec3, to: &Vertex, time: f64, rng: &mut rand::rngs::ThreadRng) -> bool {
+        let (ray, t_max) = if to.kind == Kind::Background {
+            (Ray::with_time(from, to.point, time), f64::INFINITY)
+        } else {
+            let direction = to.point - from;
+            let length = direction.length();
+            if length <= 2.0 * SURFACE_EPSILON {
+                return true;
+            }
+            (
+                Ray::with_time(from, direction, time),
+                1.0 - SURFACE_EPSILON / length,
+            )
+        };
+
+        !occluded(self.world, &ray, t_max, rng)
+    }
+
+    // radiance of the strategy joining the first s light vertices to the first
+    // t camera vertices, with t >= 2
+    fn connect(
+        &self,
+        camera_path: &[Vertex<'world>],
+        light_path: &[Vertex<'world>],
+        s: usize,
+        t: usize,
+        time: f64,
+        rng: &mut rand::rngs::ThreadRng,
+    ) -> Vec3 {
+        let zero = Vec3::new(0.0, 0.0, 0.0);
+        let pt = &camera_path[t - 1];
+
+        if s == 0 {
+            // the camera subpath found a light by itself
+            let emitted = match pt.kind {
+                Kind::Background => self.background.color(pt.point),
+                Kind::Surface => match pt.hit.and_then(|hit| hit.material.map(|m| (hit, m))) {
+                    Some((hit, material)) => material.emitted(&hit),
+                    None => zero,
+                },
+                _ => zero,
+            };
+            if emitted.near_zero() {
+                return zero;
+            }
+            let color = pt.beta * emitted;
+            return color * self.mis_weight(camera_path, light_path, None, s, t);
+        }
+        if pt.kind != Kind::Surface || pt.delta {
+            return zero;
+        }
+
+        if s == 1 {
+            // a fresh sample of the lights rather than the light path's first vertex
+            let sampled = match self.sample_light(pt, rng) {
+                Some(sampled) => sampled,
+                None => return zero,
+            };
+            let direction = pt.direction_to(&sampled);
+            let color = pt.beta * self.f(pt, direction) * sampled.beta;
+            if color.near_zero() || !self.visible(pt.point, &sampled, time, rng) {
+                return zero;
+            }
+            return color * self.mis_weight(camera_path, light_path, Some(sampled), s, t);
+        }
+
+        let qs = &light_path[s - 1];
+        if qs.delta {
+            return zero;
+        }
+        let distance_squared = (pt.point - qs.point).length_squared();
+        if distance_squared == 0.0 {
+            return zero;
+        }
+        let color =
+            qs.beta * self.f(qs, qs.direction_to(pt)) * self.f(pt, pt.direction_to(qs)) * pt.beta
+                / distance_squared;
+        if color.near_zero() || !self.visible(pt.point, qs, time, rng) {
+            return zero;
+        }
+        color * self.mis_weight(camera_path, light_path, None, s, t)
+    }
+
+    // light tracing, t = 1: the light path's last vertex seen through a point on
+    // the lens. returns the splat's s, t and color
+    fn connect_camera(
+        &self,
+        light_path: &[Vertex<'world>],
+        s: usize,
+        time: f64,
+        rng: &mut rand::rngs::ThreadRng,
+    ) -> Option<(f64, f64, Vec3)> {
+        let qs = &light_path[s - 1];
+        if qs.kind != Kind::Surface || qs.delta {
+            return None;
+        }
+        let (lens_point, weight, film_s, film_t) = self.camera.sample_importance(qs.point, rng)?;
+        if !(0.0..=self.s_max).contains(&film_s) || !(0.0..=self.t_max).contains(&film_t) {
+            return None;
+        }
+
+        let zero = Vec3::new(0.0, 0.0, 0.0);
+        let mut sampled = Vertex::new(Kind::Camera, lens_point, zero, zero, 0.0);
+        sampled.beta = Vec3::new(weight, weight, weight) * self.film_scale;
+
+        let color = qs.beta * self.f(qs, qs.direction_to(&sampled)) * sampled.beta;
+        if color.near_zero() || !self.visible(qs.point, &sampled, time, rng) {
+            return None;
+        }
+        let weight = self.mis_weight(&[], light_path, Some(sampled), s, 1);
+        Some((film_s, film_t, color * weight))
+    }
+
+    // a point on the lights or a direction towards the background for `pt` to
+    // connect to, with beta holding the emitted radiance over its pdf
+    fn sample_light(
+        &self,
+        pt: &Vertex<'world>,
+        rng: &mut rand::rngs::ThreadRng,
+    ) -> Option<Vertex<'world>> {
+        let zero = Vec3::new(0.0, 0.0, 0.0);
+        let light = match self.lights.pick(rng) {
+            Some(light) => light,
+            None => {
+                let (direction, radiance, pdf) =
+                    self.background.sample((random(rng), random(rng)))?;
+                if pdf <= 0.0 {
+                    return None;
+                }
+                let pdf = pdf * self.lights.background_probability();
+                return Some(Vertex::new(
+                    Kind::Background,
+                    direction,
+                    zero,
+                    radiance / pdf,
+                    pdf,
+                ));
+            }
+        };
+
+        let hit = light.object.sample(rng)?;
+        let to_light = hit.point - pt.point;
+        let distance_squared = to_light.length_squared();
+        if distance_squared == 0.0 {
+            return None;
+        }
+        let hit = oriented(&hit, -to_light);
+        let cos_theta = hit.geometric_normal.dot(&to_light).abs() / distance_squared.sqrt();
+        if cos_theta == 0.0 {
+            return None;
+        }
+
+        // pdf per unit solid angle as seen from pt
+        let pdf_position = self.lights.pdf_position();
+        let pdf = pdf_position * distance_squared / cos_theta;
+        let mut vertex = Vertex::new(
+            Kind::Light,
+            hit.point,
+            hit.geometric_normal,
+            light.material.emitted(&hit) / pdf,
+            pdf_position,
+        );
+        vertex.hit = Some(hit);
+        Some(vertex)
+    }
+
+    // balance heuristic over every strategy that could have built the same path,
+    // from the ratios of their pdfs to this one's, as in pbrt
+    fn mis_weight(
+        &self,
+        camera_path: &[Vertex<'world>],
+        light_path: &[Vertex<'world>],
+        sampled: Option<Vertex<'world>>,
+        s: usize,
+        t: usize,
+    ) -> f64 {
+        if s + t == 2 {
+            return 1.0;
+        }
+        let mut camera = match (t, sampled) {
+            (1, Some(sampled)) => vec![sampled],
+            _ => camera_path[..t].to_vec(),
+        };
+        let mut light = match (s, sampled) {
+            (1, Some(sampled)) => vec![sampled],
+            _ => light_path[..s].to_vec(),
+        };
+
+        let pt = t - 1;
+        if s == 0 {
+            let emitter = camera[pt];
+            if let Some(material) = emitter.hit.and_then(|hit| hit.material) {
+                // not among the lights, so nothing but the camera path finds it
+                if !self.lights.contains(material) {
+                    return 1.0;
+                }
+            }
+        }
+
+        // the connection's vertices, scattering through them is no longer specular
+        // and their reverse pdfs come from the other side of the connection
+        camera[pt].delta = false;
+        camera[pt].pdf_rev = if s > 0 {
+            let prev = if s > 1 { Some(&light[s - 2]) } else { None };
+            self.pdf(&light[s - 1], prev, &camera[pt])
+        } else {
+            self.pdf_light_origin(&camera[pt])
+        };
+        if t > 1 {
+            camera[pt - 1].pdf_rev = if s > 0 {
+                self.pdf(&camera[pt], Some(&light[s - 1]), &camera[pt - 1])
+            } else {
+                self.pdf_light(&camera[pt], &camera[pt - 1])
+            };
+        }
+        if s > 0 {
+            let qs = s - 1;
+            light[qs].delta = false;
+            let prev = if t > 1 { Some(&camera[pt - 1]) } else { None };
+            light[qs].pdf_rev = self.pdf(&camera[pt], prev, &light[qs]);
+            if s > 1 {
+                light[qs - 1].pdf_rev = self.pdf(&light[qs], Some(&camera[pt]), &light[qs - 1]);
+            }
+        }
+
+        // light subpaths never start at the background, so paths ending there only
+        // come from the camera path escaping or the background being sampled
+        let background = if s == 0 {
+            camera[pt].kind == Kind::Background
+        } else {
+            light[0].kind == Kind::Background
+        };
+
+        let mut sum = 0.0;
+        let mut ratio = 1.0;
+        for i in (1..t).rev() {
+            if background && s + t - i >= 2 {
+                break;
+            }
+            ratio *= remap(camera[i].pdf_rev) / remap(camera[i].pdf_fwd);
+            // the camera can't be connected to without importance
+            let light_tracing = i == 1 && !self.camera.has_importance();
+            if !camera[i].delta && !camera[i - 1].delta && !light_tracing {
+                sum += ratio;
+            }
+        }
+
+        let mut ratio = 1.0;
+        for i in (0..s).rev() {
+            ratio *= remap(light[i].pdf_rev) / remap(light[i].pdf_fwd);
+            let delta_light = i > 0 && light[i - 1].delta;
+            if !light[i].delta && !delta_light {
+                sum += ratio;
+            }
+        }
+        1.0 / (1.0 + sum)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lambertian::Lambertian;
+    use crate::render::{white_furnace, Integrator, RenderSettings};
+    use crate::vec::Vec3;
+
+    #[test]
+    fn white_furnace_keeps_energy() {
+        let mut settings = RenderSettings::new(8, 8, 32, 4);
+        settings.integrator = Integrator::Bidirectional;
+        let white = Box::new(Lambertian::new(Vec3::new(1.0, 1.0, 1.0)));
+
+        let mean = white_furnace(white, &settings);
+        assert!((mean - 1.0).abs() < 0.03, "furnace mean {}", mean);
+    }
+}
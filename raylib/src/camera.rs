@@ -1,8 +1,36 @@
+use crate::aperture::Aperture;
 use crate::degrees_to_radians;
-use crate::random_in_unit_disk;
+use crate::lens_system::LensSystem;
+use crate::random;
 use crate::ray::Ray;
 use crate::vec::Vec3;
 
+// how directions around the camera map onto the image
+#[derive(Copy, Clone, Debug)]
+pub enum Projection {
+    Perspective,
+    // parallel rays along the view direction, for elevation views without
+    // foreshortening. the image covers horizontal and vertical around the origin
+    Orthographic,
+    // the view circle fills the image height, fov being the full angle across it
+    Fisheye { fov: f64, mapping: FisheyeMapping },
+    // longitude across and latitude up the full sphere, the view direction in the
+    // middle, for 360 degree panoramas
+    Equirectangular,
+    // equirectangular seen from one eye of a head turning to face every
+    // direction, offset along the eye's right by the signed distance from the
+    // head's center (peleg et al., 2001). for stereo panoramas
+    OmniStereo { offset: f64 },
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum FisheyeMapping {
+    // distance from the center grows with the angle off the view direction
+    Equidistant,
+    // equal areas of the image see equal solid angles
+    Equisolid,
+}
+
 pub struct Camera {
     pub origin: Vec3,
     pub lower_left_corner: Vec3,
@@ -12,6 +40,20 @@ pub struct Camera {
     pub u: Vec3,
     pub v: Vec3,
     pub lens_radius: f64,
+    pub projection: Projection,
+    // scale from radiance to pixel values, 1 unless the camera has exposure settings
+    pub exposure: f64,
+    pub aperture: Aperture,
+    // optical vignetting: how far the lens barrel's opening shifts across the
+    // aperture at the image corners, in lens radii, turning out of focus
+    // highlights there into cat's eyes. 0 turns it off
+    pub cat_eye: f64,
+    // a real lens rays are traced through instead of the projection
+    pub lens_system: Option<LensSystem>,
+    // when the shutter opens and closes, in seconds. rays are spread over the
+    // time in between, which blurs animated objects
+    pub shutter_open: f64,
+    pub shutter_close: f64,
 }
 
 fn build_camera(viewport_width: f64, viewport_height: f64) -> Camera {
@@ -29,10 +71,17 @@ fn build_camera(viewport_width: f64, viewport_height: f64) -> Camera {
             - horizontal / 2.0
             - vertical / 2.0
             - Vec3::new(0.0, 0.0, focal_length),
-        w: Vec3::default(),
-        u: Vec3::default(),
-        v: Vec3::default(),
+        w: Vec3::new(0.0, 0.0, 1.0),
+        u: Vec3::new(1.0, 0.0, 0.0),
+        v: Vec3::new(0.0, 1.0, 0.0),
         lens_radius: 0.0,
+        projection: Projection::Perspective,
+        exposure: 1.0,
+        aperture: Aperture::Circle,
+        cat_eye: 0.0,
+        lens_system: None,
+        shutter_open: 0.0,
+        shutter_close: 0.0,
     }
 }
 
@@ -79,10 +128,17 @@ impl Camera {
             horizontal,
             vertical,
             lower_left_corner: origin - (horizontal / 2.0) - (vertical / 2.0) - w,
-            w: Vec3::default(),
-            u: Vec3::default(),
-            v: Vec3::default(),
+            w,
+            u,
+            v,
             lens_radius: 0.0,
+            projection: Projection::Perspective,
+            exposure: 1.0,
+            aperture: Aperture::Circle,
+            cat_eye: 0.0,
+            lens_system: None,
+            shutter_open: 0.0,
+            shutter_close: 0.0,
         }
     }
 
@@ -118,26 +174,387 @@ impl Camera {
             u,
             v,
             lens_radius: aperture / 2.0,
+            projection: Projection::Perspective,
+            exposure: 1.0,
+            aperture: Aperture::Circle,
+            cat_eye: 0.0,
+            lens_system: None,
+            shutter_open: 0.0,
+            shutter_close: 0.0,
+        }
+    }
+
+    // view_height is how much of the world the image covers vertically
+    pub fn orthographic(
+        look_from: Vec3,
+        look_at: Vec3,
+        view_up: Vec3,
+        view_height: f64,
+        aspect_ratio: f64,
+    ) -> Camera {
+        let (w, u, v) = Camera::frame(look_from, look_at, view_up);
+        let horizontal = u * view_height * aspect_ratio;
+        let vertical = v * view_height;
+
+        Camera {
+            origin: look_from,
+            lower_left_corner: look_from - horizontal / 2.0 - vertical / 2.0,
+            horizontal,
+            vertical,
+            w,
+            u,
+            v,
+            lens_radius: 0.0,
+            projection: Projection::Orthographic,
+            exposure: 1.0,
+            aperture: Aperture::Circle,
+            cat_eye: 0.0,
+            lens_system: None,
+            shutter_open: 0.0,
+            shutter_close: 0.0,
+        }
+    }
+
+    pub fn fisheye(
+        look_from: Vec3,
+        look_at: Vec3,
+        view_up: Vec3,
+        fov: f64,
+        aspect_ratio: f64,
+        mapping: FisheyeMapping,
+    ) -> Camera {
+        let (w, u, v) = Camera::frame(look_from, look_at, view_up);
+
+        // a unit image circle at unit distance, wider as the aspect ratio says
+        let horizontal = u * 2.0 * aspect_ratio;
+        let vertical = v * 2.0;
+
+        Camera {
+            origin: look_from,
+            lower_left_corner: look_from - horizontal / 2.0 - vertical / 2.0 - w,
+            horizontal,
+            vertical,
+            w,
+            u,
+            v,
+            lens_radius: 0.0,
+            projection: Projection::Fisheye {
+                fov: degrees_to_radians(fov),
+                mapping,
+            },
+            exposure: 1.0,
+            aperture: Aperture::Circle,
+            cat_eye: 0.0,
+            lens_system: None,
+            shutter_open: 0.0,
+            shutter_close: 0.0,
         }
     }
 
+    // the image should be twice as wide as it is high
+    pub fn equirectangular(look_from: Vec3, look_at: Vec3, view_up: Vec3) -> Camera {
+        let (w, u, v) = Camera::frame(look_from, look_at, view_up);
+        let horizontal = u * 2.0;
+        let vertical = v;
+
+        Camera {
+            origin: look_from,
+            lower_left_corner: look_from - horizontal / 2.0 - vertical / 2.0 - w,
+            horizontal,
+            vertical,
+            w,
+            u,
+            v,
+            lens_radius: 0.0,
+            projection: Projection::Equirectangular,
+            exposure: 1.0,
+            aperture: Aperture::Circle,
+            cat_eye: 0.0,
+            lens_system: None,
+            shutter_open: 0.0,
+            shutter_close: 0.0,
+        }
+    }
+
+    // a camera looking through a lens system, focused at focus_distance from the
+    // film. the scene is in meters and the sensor's diagonal in millimeters like
+    // the prescription. the exposure is set so the image center is as bright as
+    // through a thin lens, leaving the corners darker where the lens vignettes
+    pub fn realistic(
+        look_from: Vec3,
+        look_at: Vec3,
+        view_up: Vec3,
+        lens_system: LensSystem,
+        sensor_diagonal: f64,
+        aspect_ratio: f64,
+        focus_distance: f64,
+    ) -> Camera {
+        let (w, u, v) = Camera::frame(look_from, look_at, view_up);
+        let height = sensor_diagonal / 1000.0 / (aspect_ratio * aspect_ratio + 1.0).sqrt();
+        let horizontal = u * (aspect_ratio * height);
+        let vertical = v * height;
+
+        let mut lens_system = lens_system;
+        lens_system.focus(focus_distance);
+        let transmission = lens_system.transmission(0.0, 0.0);
+        if transmission <= 0.0 {
+            panic!("error no light gets through the lens system");
+        }
+
+        Camera {
+            origin: look_from,
+            lower_left_corner: look_from - horizontal / 2.0 - vertical / 2.0,
+            horizontal,
+            vertical,
+            w,
+            u,
+            v,
+            lens_radius: 0.0,
+            projection: Projection::Perspective,
+            exposure: 1.0 / transmission,
+            aperture: Aperture::Circle,
+            cat_eye: 0.0,
+            lens_system: Some(lens_system),
+            shutter_open: 0.0,
+            shutter_close: 0.0,
+        }
+    }
+
+    // w points back from where the camera looks, u right and v up
+    fn frame(look_from: Vec3, look_at: Vec3, view_up: Vec3) -> (Vec3, Vec3, Vec3) {
+        let w = (look_from - look_at).unit();
+        let u = view_up.cross(&w).unit();
+        let v = w.cross(&u);
+        (w, u, v)
+    }
+
+    // ray for the image position s, t in the camera's projection, None where the
+    // projection doesn't cover the image, such as outside a fisheye's circle
+    pub fn sample_ray(&self, s: f64, t: f64, rng: &mut rand::rngs::ThreadRng) -> Option<Ray> {
+        let lens = (random(rng), random(rng));
+        let time = random(rng);
+        self.generate_ray(s, t, lens, time, rng)
+    }
+
+    // like sample_ray, with the point on the lens and the moment the shutter is
+    // open for given as numbers in [0, 1), which a sampler supplies
+    pub fn generate_ray(
+        &self,
+        s: f64,
+        t: f64,
+        lens: (f64, f64),
+        time: f64,
+        rng: &mut rand::rngs::ThreadRng,
+    ) -> Option<Ray> {
+        let mut ray = self.projected_ray(s, t, lens, rng)?;
+        ray.time = self.shutter_time(time);
+        Some(ray)
+    }
+
+    // a moment while the shutter is open
+    pub fn sample_time(&self, rng: &mut rand::rngs::ThreadRng) -> f64 {
+        self.shutter_time(random(rng))
+    }
+
+    fn shutter_time(&self, u: f64) -> f64 {
+        if self.shutter_close > self.shutter_open {
+            self.shutter_open + u * (self.shutter_close - self.shutter_open)
+        } else {
+            self.shutter_open
+        }
+    }
+
+    fn projected_ray(
+        &self,
+        s: f64,
+        t: f64,
+        lens: (f64, f64),
+        rng: &mut rand::rngs::ThreadRng,
+    ) -> Option<Ray> {
+        let (x, y) = (s - 0.5, t - 0.5);
+        if let Some(lens_system) = &self.lens_system {
+            // the lens flips the image, so the film's right side sees the left
+            let film_x = -x * self.horizontal.length();
+            let film_y = -y * self.vertical.length();
+            let (origin, direction) = lens_system.sample(film_x, film_y, lens, rng)?;
+            return Some(Ray::new(
+                self.origin + self.u * origin.x + self.v * origin.y + self.w * origin.z,
+                self.u * direction.x + self.v * direction.y + self.w * direction.z,
+            ));
+        }
+        match self.projection {
+            Projection::Perspective => {
+                let lens = self.aperture.sample_point(lens, rng);
+                if self.cat_eye > 0.0 {
+                    // the barrel's opening, a disk shifted towards the image's edge
+                    let aspect = self.horizontal.length() / self.vertical.length();
+                    let corner = (aspect * aspect + 1.0).sqrt();
+                    let shift_x = 2.0 * x * aspect / corner * self.cat_eye;
+                    let shift_y = 2.0 * y / corner * self.cat_eye;
+                    let (dx, dy) = (lens.0 - shift_x, lens.1 - shift_y);
+                    if dx * dx + dy * dy > 1.0 {
+                        return None;
+                    }
+                }
+                Some(self.lens_ray(s, t, lens))
+            }
+            Projection::Orthographic => Some(Ray::new(
+                self.lower_left_corner + self.horizontal * s + self.vertical * t,
+                -self.w,
+            )),
+            Projection::Fisheye { fov, mapping } => {
+                let x = x * self.horizontal.length();
+                let y = y * self.vertical.length();
+                let r = (x * x + y * y).sqrt();
+                if r > 1.0 {
+                    return None;
+                }
+                let theta = match mapping {
+                    FisheyeMapping::Equidistant => r * fov / 2.0,
+                    FisheyeMapping::Equisolid => 2.0 * (r * (fov / 4.0).sin()).asin(),
+                };
+                let phi = y.atan2(x);
+                let direction = self.u * (theta.sin() * phi.cos())
+                    + self.v * (theta.sin() * phi.sin())
+                    - self.w * theta.cos();
+                Some(Ray::new(self.origin, direction))
+            }
+            Projection::Equirectangular | Projection::OmniStereo { .. } => {
+                let phi = x * 2.0 * std::f64::consts::PI;
+                let theta = y * std::f64::consts::PI;
+                let direction = self.u * (theta.cos() * phi.sin()) + self.v * theta.sin()
+                    - self.w * (theta.cos() * phi.cos());
+                let origin = match self.projection {
+                    Projection::OmniStereo { offset } => {
+                        self.origin + (self.u * phi.cos() + self.w * phi.sin()) * offset
+                    }
+                    _ => self.origin,
+                };
+                Some(Ray::new(origin, direction))
+            }
+        }
+    }
+
+    // whether importance is defined, which light tracing needs to reach the image.
+    // only the perspective projection has it, and only through a thin lens
+    // without cat's eyes
+    pub fn has_importance(&self) -> bool {
+        matches!(self.projection, Projection::Perspective)
+            && self.cat_eye <= 0.0
+            && self.lens_system.is_none()
+    }
+
     pub fn ray(&self, s: f64, t: f64) -> Ray {
         Ray {
             origin: self.origin,
             direction: self.lower_left_corner + self.horizontal * s + self.vertical * t
                 - self.origin,
+            time: 0.0,
         }
     }
 
     pub fn ray_12_2(&self, s: f64, t: f64, rng: &mut rand::rngs::ThreadRng) -> Ray {
-        let rd = random_in_unit_disk(rng) * self.lens_radius;
-        let offset = self.u * rd.x + self.v * rd.y;
+        self.lens_ray(s, t, self.aperture.sample(rng))
+    }
+
+    // ray through the point on the lens, given on the unit disk's scale
+    fn lens_ray(&self, s: f64, t: f64, lens: (f64, f64)) -> Ray {
+        let offset = self.u * (lens.0 * self.lens_radius) + self.v * (lens.1 * self.lens_radius);
 
         Ray {
             origin: self.origin + offset,
             direction: self.lower_left_corner + self.horizontal * s + self.vertical * t
                 - self.origin
                 - offset,
+            time: 0.0,
+        }
+    }
+
+    // where the image plane sits in focus, seen from the lens
+    fn image_center(&self) -> Vec3 {
+        self.lower_left_corner + self.horizontal / 2.0 + self.vertical / 2.0
+    }
+
+    // normal of the image plane, which the image's center can be off of in an
+    // off-axis stereo eye
+    fn forward(&self) -> Vec3 {
+        -self.w
+    }
+
+    fn image_distance(&self) -> f64 {
+        (self.image_center() - self.origin).dot(&self.forward())
+    }
+
+    // a pinhole counts as a lens of unit area, which keeps the importance finite
+    fn lens_area(&self) -> f64 {
+        if self.lens_radius > 0.0 {
+            self.aperture.area() * self.lens_radius * self.lens_radius
+        } else {
+            1.0
+        }
+    }
+
+    // importance of a ray leaving the lens along the unit `direction`, normalized
+    // so it integrates to one over the image at s, t in [0, 1], and the s, t
+    // ray_12_2 would have generated it with. None for rays going backwards
+    pub fn importance(&self, lens_point: Vec3, direction: Vec3) -> Option<(f64, f64, f64)> {
+        if !self.has_importance() {
+            return None;
+        }
+        let cos_theta = direction.dot(&self.forward());
+        if cos_theta <= 0.0 {
+            return None;
+        }
+        let distance = self.image_distance();
+        let focus_point = lens_point + direction * (distance / cos_theta);
+
+        let relative = focus_point - self.lower_left_corner;
+        let s = relative.dot(&self.horizontal) / self.horizontal.length_squared();
+        let t = relative.dot(&self.vertical) / self.vertical.length_squared();
+
+        let area = self.horizontal.length() * self.vertical.length();
+        let cos2 = cos_theta * cos_theta;
+        let importance = distance * distance / (area * self.lens_area() * cos2 * cos2);
+        Some((importance, s, t))
+    }
+
+    // pdfs of ray_12_2 picking the lens point, per unit area, and the unit
+    // direction, per unit solid angle, when s, t are uniform over [0, 1]
+    pub fn pdf_importance(&self, direction: Vec3) -> (f64, f64) {
+        if !self.has_importance() {
+            return (0.0, 0.0);
+        }
+        let cos_theta = direction.dot(&self.forward());
+        if cos_theta <= 0.0 {
+            return (0.0, 0.0);
         }
+        let distance = self.image_distance();
+        let area = self.horizontal.length() * self.vertical.length();
+
+        (
+            1.0 / self.lens_area(),
+            distance * distance / (area * cos_theta * cos_theta * cos_theta),
+        )
+    }
+
+    // picks a point on the lens for `point` to be seen through, returning the lens
+    // point, the importance arriving at `point` over the pdf of picking it per
+    // unit solid angle there, and the s, t of the ray from the lens to `point`
+    pub fn sample_importance(
+        &self,
+        point: Vec3,
+        rng: &mut rand::rngs::ThreadRng,
+    ) -> Option<(Vec3, f64, f64, f64)> {
+        let (x, y) = self.aperture.sample(rng);
+        let lens_point =
+            self.origin + self.u * (x * self.lens_radius) + self.v * (y * self.lens_radius);
+
+        let to_point = point - lens_point;
+        let direction = to_point.unit();
+        let (importance, s, t) = self.importance(lens_point, direction)?;
+
+        let cos_theta = direction.dot(&self.forward());
+        let pdf = to_point.length_squared() / (cos_theta * self.lens_area());
+        Some((lens_point, importance / pdf, s, t))
     }
 }
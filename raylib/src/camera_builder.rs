@@ -0,0 +1,366 @@
+use crate::aperture::Aperture;
+use crate::camera::Camera;
+use crate::exposure::{ev100, exposure_scale};
+use crate::vec::Vec3;
+
+use std::fmt;
+
+// what's wrong with the settings a camera was built from
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CameraError {
+    // look_from and look_at are the same point
+    NoViewDirection,
+    // view_up is parallel to the view direction, so it doesn't say where up is
+    ViewUpParallel,
+    FieldOfView(f64),
+    FocalLength(f64),
+    SensorSize(f64, f64),
+    Aperture(f64),
+    FocusDistance(f64),
+    AspectRatio(f64),
+    FNumber(f64),
+    // an f-number only means something for a lens with a focal length
+    FNumberWithoutFocalLength,
+    Shutter(f64),
+    Iso(f64),
+    CatEye(f64),
+    ShutterOpen(f64),
+}
+
+impl fmt::Display for CameraError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CameraError::NoViewDirection => write!(f, "look from and look at are the same point"),
+            CameraError::ViewUpParallel => write!(f, "view up is parallel to the view direction"),
+            CameraError::FieldOfView(fov) => {
+                write!(f, "field of view {} is not between 0 and 180 degrees", fov)
+            }
+            CameraError::FocalLength(length) => {
+                write!(f, "focal length {} is not positive", length)
+            }
+            CameraError::SensorSize(width, height) => {
+                write!(f, "sensor size {} x {} is not positive", width, height)
+            }
+            CameraError::Aperture(aperture) => {
+                write!(f, "aperture {} is not zero or positive", aperture)
+            }
+            CameraError::FocusDistance(distance) => {
+                write!(f, "focus distance {} is not positive", distance)
+            }
+            CameraError::AspectRatio(ratio) => write!(f, "aspect ratio {} is not positive", ratio),
+            CameraError::FNumber(f_number) => write!(f, "f-number {} is not positive", f_number),
+            CameraError::FNumberWithoutFocalLength => {
+                write!(f, "f-number is set without a focal length")
+            }
+            CameraError::Shutter(shutter) => write!(f, "shutter time {} is not positive", shutter),
+            CameraError::Iso(iso) => write!(f, "iso {} is not positive", iso),
+            CameraError::CatEye(cat_eye) => {
+                write!(f, "cat's eye {} is not zero or positive", cat_eye)
+            }
+            CameraError::ShutterOpen(time) => write!(f, "shutter open time {} is not finite", time),
+        }
+    }
+}
+
+impl std::error::Error for CameraError {}
+
+fn positive(x: f64) -> bool {
+    x > 0.0 && x.is_finite()
+}
+
+#[derive(Copy, Clone, Debug)]
+enum FieldOfView {
+    // vertical, in degrees
+    Vertical(f64),
+    // focal length and sensor width and height, all in millimeters
+    Lens(f64, f64, f64),
+}
+
+// sets up a thin lens camera in any order and checks the settings fit together,
+// where the chapter constructors each fill in only what their chapter needs.
+// unset values default to looking down -z from the origin with a 90 degree
+// field of view, a pinhole, focus on look_at and a 16:9 image. setting an
+// f-number makes it a physical camera: the lens opening follows from the focal
+// length, taking the scene to be in meters, and the exposure from the f-number,
+// shutter time and iso. with or without one, the shutter stays open for the
+// shutter time from the shutter open time, 0 unless set, blurring animated
+// objects
+#[derive(Clone)]
+pub struct CameraBuilder {
+    look_from: Vec3,
+    look_at: Vec3,
+    view_up: Vec3,
+    field_of_view: FieldOfView,
+    aperture: f64,
+    focus_distance: Option<f64>,
+    aspect_ratio: Option<f64>,
+    f_number: Option<f64>,
+    shutter: f64,
+    shutter_open: f64,
+    iso: f64,
+    aperture_shape: Aperture,
+    cat_eye: f64,
+}
+
+impl CameraBuilder {
+    pub fn new() -> CameraBuilder {
+        CameraBuilder {
+            look_from: Vec3::new(0.0, 0.0, 0.0),
+            look_at: Vec3::new(0.0, 0.0, -1.0),
+            view_up: Vec3::new(0.0, 1.0, 0.0),
+            field_of_view: FieldOfView::Vertical(90.0),
+            aperture: 0.0,
+            focus_distance: None,
+            aspect_ratio: None,
+            f_number: None,
+            shutter: 1.0 / 125.0,
+            shutter_open: 0.0,
+            iso: 100.0,
+            aperture_shape: Aperture::Circle,
+            cat_eye: 0.0,
+        }
+    }
+
+    pub fn look_from(mut self, look_from: Vec3) -> CameraBuilder {
+        self.look_from = look_from;
+        self
+    }
+
+    pub fn look_at(mut self, look_at: Vec3) -> CameraBuilder {
+        self.look_at = look_at;
+        self
+    }
+
+    pub fn view_up(mut self, view_up: Vec3) -> CameraBuilder {
+        self.view_up = view_up;
+        self
+    }
+
+    // vertical field of view in degrees
+    pub fn vfov(mut self, vfov: f64) -> CameraBuilder {
+        self.field_of_view = FieldOfView::Vertical(vfov);
+        self
+    }
+
+    // field of view from a lens on a sensor, in millimeters. the aspect ratio
+    // follows the sensor unless it's set
+    pub fn focal_length(
+        mut self,
+        focal_length: f64,
+        sensor_width: f64,
+        sensor_height: f64,
+    ) -> CameraBuilder {
+        self.field_of_view = FieldOfView::Lens(focal_length, sensor_width, sensor_height);
+        self
+    }
+
+    // diameter of the lens in world units, 0 for a pinhole
+    pub fn aperture(mut self, aperture: f64) -> CameraBuilder {
+        self.aperture = aperture;
+        self
+    }
+
+    pub fn focus_distance(mut self, focus_distance: f64) -> CameraBuilder {
+        self.focus_distance = Some(focus_distance);
+        self
+    }
+
+    pub fn aspect_ratio(mut self, aspect_ratio: f64) -> CameraBuilder {
+        self.aspect_ratio = Some(aspect_ratio);
+        self
+    }
+
+    // replaces the aperture with focal length / f-number
+    pub fn f_number(mut self, f_number: f64) -> CameraBuilder {
+        self.f_number = Some(f_number);
+        self
+    }
+
+    // in seconds
+    pub fn shutter(mut self, shutter: f64) -> CameraBuilder {
+        self.shutter = shutter;
+        self
+    }
+
+    // when the shutter opens in seconds, the time of the frame being rendered
+    pub fn shutter_open(mut self, time: f64) -> CameraBuilder {
+        self.shutter_open = time;
+        self
+    }
+
+    pub fn iso(mut self, iso: f64) -> CameraBuilder {
+        self.iso = iso;
+        self
+    }
+
+    // shape of the lens opening, circular unless set
+    pub fn aperture_shape(mut self, aperture_shape: Aperture) -> CameraBuilder {
+        self.aperture_shape = aperture_shape;
+        self
+    }
+
+    // how far towards the image corners out of focus highlights get cut into
+    // cat's eyes, in lens radii
+    pub fn cat_eye(mut self, cat_eye: f64) -> CameraBuilder {
+        self.cat_eye = cat_eye;
+        self
+    }
+
+    pub fn build(&self) -> Result<Camera, CameraError> {
+        let view = self.look_at - self.look_from;
+        if view.length_squared() == 0.0 {
+            return Err(CameraError::NoViewDirection);
+        }
+        if self.view_up.length_squared() == 0.0
+            || self.view_up.unit().cross(&view.unit()).length() <= 1e-6
+        {
+            return Err(CameraError::ViewUpParallel);
+        }
+
+        let (vfov, sensor_ratio) = match self.field_of_view {
+            FieldOfView::Vertical(vfov) => (vfov, None),
+            FieldOfView::Lens(focal_length, width, height) => {
+                if !positive(focal_length) {
+                    return Err(CameraError::FocalLength(focal_length));
+                }
+                if !positive(width) || !positive(height) {
+                    return Err(CameraError::SensorSize(width, height));
+                }
+                let vfov = 2.0 * (height / (2.0 * focal_length)).atan();
+                (vfov.to_degrees(), Some(width / height))
+            }
+        };
+        if !positive(vfov) || vfov >= 180.0 {
+            return Err(CameraError::FieldOfView(vfov));
+        }
+
+        if self.aperture < 0.0 || !self.aperture.is_finite() {
+            return Err(CameraError::Aperture(self.aperture));
+        }
+        if !positive(self.shutter) {
+            return Err(CameraError::Shutter(self.shutter));
+        }
+        let (aperture, exposure) = match (self.f_number, self.field_of_view) {
+            (None, _) => (self.aperture, 1.0),
+            (Some(_), FieldOfView::Vertical(_)) => {
+                return Err(CameraError::FNumberWithoutFocalLength)
+            }
+            (Some(f_number), FieldOfView::Lens(focal_length, _, _)) => {
+                if !positive(f_number) {
+                    return Err(CameraError::FNumber(f_number));
+                }
+                if !positive(self.iso) {
+                    return Err(CameraError::Iso(self.iso));
+                }
+                // millimeters to meters
+                let aperture = focal_length / f_number / 1000.0;
+                (
+                    aperture,
+                    exposure_scale(ev100(f_number, self.shutter, self.iso)),
+                )
+            }
+        };
+        if self.cat_eye < 0.0 || !self.cat_eye.is_finite() {
+            return Err(CameraError::CatEye(self.cat_eye));
+        }
+        if !self.shutter_open.is_finite() {
+            return Err(CameraError::ShutterOpen(self.shutter_open));
+        }
+        let focus_distance = self.focus_distance.unwrap_or_else(|| view.length());
+        if !positive(focus_distance) {
+            return Err(CameraError::FocusDistance(focus_distance));
+        }
+        let aspect_ratio = self.aspect_ratio.or(sensor_ratio).unwrap_or(16.0 / 9.0);
+        if !positive(aspect_ratio) {
+            return Err(CameraError::AspectRatio(aspect_ratio));
+        }
+
+        let mut camera = Camera::new_12_2(
+            self.look_from,
+            self.look_at,
+            self.view_up,
+            vfov,
+            aspect_ratio,
+            aperture,
+            focus_distance,
+        );
+        camera.exposure = exposure;
+        camera.shutter_open = self.shutter_open;
+        camera.shutter_close = self.shutter_open + self.shutter;
+        camera.aperture = self.aperture_shape.clone();
+        camera.cat_eye = self.cat_eye;
+        Ok(camera)
+    }
+}
+
+impl Default for CameraBuilder {
+    fn default() -> CameraBuilder {
+        CameraBuilder::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(builder: CameraBuilder) -> Option<CameraError> {
+        builder.build().err()
+    }
+
+    fn lens() -> CameraBuilder {
+        CameraBuilder::new().focal_length(50.0, 36.0, 24.0)
+    }
+
+    #[test]
+    fn defaults_build() {
+        assert!(CameraBuilder::new().build().is_ok());
+        assert!(lens().f_number(2.8).build().is_ok());
+    }
+
+    #[test]
+    fn every_error_for_its_bad_setting() {
+        let b = CameraBuilder::new;
+        let origin = Vec3::new(0.0, 0.0, 0.0);
+        let cases = [
+            (b().look_at(origin), CameraError::NoViewDirection),
+            (
+                b().view_up(Vec3::new(0.0, 0.0, 2.0)),
+                CameraError::ViewUpParallel,
+            ),
+            (b().vfov(180.0), CameraError::FieldOfView(180.0)),
+            (
+                b().focal_length(-50.0, 36.0, 24.0),
+                CameraError::FocalLength(-50.0),
+            ),
+            (
+                b().focal_length(50.0, 36.0, 0.0),
+                CameraError::SensorSize(36.0, 0.0),
+            ),
+            (b().aperture(-1.0), CameraError::Aperture(-1.0)),
+            (b().focus_distance(0.0), CameraError::FocusDistance(0.0)),
+            (b().aspect_ratio(-1.5), CameraError::AspectRatio(-1.5)),
+            (lens().f_number(0.0), CameraError::FNumber(0.0)),
+            (b().f_number(2.8), CameraError::FNumberWithoutFocalLength),
+            (b().shutter(0.0), CameraError::Shutter(0.0)),
+            (lens().f_number(2.8).iso(-100.0), CameraError::Iso(-100.0)),
+            (b().cat_eye(-0.5), CameraError::CatEye(-0.5)),
+            (
+                b().shutter_open(f64::INFINITY),
+                CameraError::ShutterOpen(f64::INFINITY),
+            ),
+        ];
+        for (builder, expected) in cases {
+            assert_eq!(error(builder), Some(expected));
+        }
+    }
+
+    #[test]
+    fn shutter_stays_open_for_the_shutter_time() {
+        let camera = CameraBuilder::new()
+            .shutter(0.5)
+            .shutter_open(2.0)
+            .build()
+            .unwrap();
+        assert_eq!((camera.shutter_open, camera.shutter_close), (2.0, 2.5));
+    }
+}
@@ -0,0 +1,289 @@
+use crate::hittable::Hit;
+use crate::material::Material;
+use crate::microfacet::{fresnel_dielectric, Ggx};
+use crate::onb::Onb;
+use crate::random;
+use crate::ray::Ray;
+use crate::spectrum::{Color, SampledSpectrum, Wavelengths};
+use crate::thin_film::ThinFilm;
+use crate::vec::Vec3;
+
+// scattered ray, attenuation and pdf, as returned by Material::scatter_with_pdf
+type Scattered<C> = Option<(Ray, C, Option<f64>)>;
+
+// clear coat over another material, like car paint or varnished wood. light either
+// reflects off the dielectric coat or passes through it twice, tinted by the coat,
+// and scatters off the base in between. internal reflections inside the coat are
+// ignored. an optional thin film on top of the coat adds interference colors
+pub struct Coated {
+    pub base: Box<dyn Material>,
+    pub index_of_refraction: f64,
+    pub distribution: Ggx,
+    // color of the coat seen through it at normal incidence
+    pub tint: Vec3,
+    pub film: Option<ThinFilm>,
+}
+
+impl Coated {
+    pub fn new(base: Box<dyn Material>, index_of_refraction: f64, roughness: f64) -> Coated {
+        Coated {
+            base,
+            index_of_refraction,
+            distribution: Ggx::new(roughness),
+            tint: Vec3::new(1.0, 1.0, 1.0),
+            film: None,
+        }
+    }
+
+    pub fn tinted(
+        base: Box<dyn Material>,
+        index_of_refraction: f64,
+        roughness: f64,
+        tint: Vec3,
+    ) -> Coated {
+        Coated {
+            tint,
+            ..Coated::new(base, index_of_refraction, roughness)
+        }
+    }
+
+    pub fn with_film(
+        base: Box<dyn Material>,
+        index_of_refraction: f64,
+        roughness: f64,
+        film: ThinFilm,
+    ) -> Coated {
+        Coated {
+            film: Some(film),
+            ..Coated::new(base, index_of_refraction, roughness)
+        }
+    }
+
+    fn reflectance_rgb(&self, cos_theta: f64) -> Vec3 {
+        match self.film {
+            Some(film) => film.reflectance_rgb(cos_theta, self.index_of_refraction),
+            None => Vec3::splat(fresnel_dielectric(cos_theta, self.index_of_refraction)),
+        }
+    }
+
+    fn reflectance_spectral(&self, cos_theta: f64, wavelengths: &Wavelengths) -> SampledSpectrum {
+        match self.film {
+            Some(film) => {
+                film.reflectance_spectral(cos_theta, self.index_of_refraction, wavelengths)
+            }
+            None => SampledSpectrum::new(fresnel_dielectric(cos_theta, self.index_of_refraction)),
+        }
+    }
+
+    // tint along the refracted path down through the coat and back up
+    fn transmittance(&self, cos_o: f64, cos_i: f64) -> Vec3 {
+        let refracted = |cos: f64| {
+            let sin2 = (1.0 - cos * cos) / (self.index_of_refraction * self.index_of_refraction);
+            (1.0 - sin2).max(1e-4).sqrt()
+        };
+        let exponent = 0.5 * (1.0 / refracted(cos_o) + 1.0 / refracted(cos_i));
+
+        Vec3::new(
+            self.tint.x.powf(exponent),
+            self.tint.y.powf(exponent),
+            self.tint.z.powf(exponent),
+        )
+    }
+
+    // coat reflection as bsdf times cosine and pdf, zero for a smooth coat
+    fn coat_eval(&self, wo: Vec3, wi: Vec3) -> (f64, f64) {
+        if self.distribution.is_smooth() || wo.z <= 0.0 || wi.z <= 0.0 {
+            return (0.0, 0.0);
+        }
+        let m = (wo + wi).unit();
+        let d = self.distribution.d(m);
+
+        (
+            d * self.distribution.g(wo, wi) / (4.0 * wo.z),
+            self.distribution.g1(wo) * d / (4.0 * wo.z),
+        )
+    }
+
+    // picks the coat with the probability of it reflecting, otherwise the base
+    #[allow(clippy::too_many_arguments)]
+    fn scatter_layers<C: Color>(
+        &self,
+        ray: &Ray,
+        hit: &Hit,
+        rng: &mut rand::rngs::ThreadRng,
+        reflectance: &dyn Fn(f64) -> C,
+        to_color: &dyn Fn(Vec3) -> C,
+        scatter_base: &mut dyn FnMut(&mut rand::rngs::ThreadRng) -> Scattered<C>,
+        eval_base: &dyn Fn(Vec3) -> Option<(C, f64)>,
+    ) -> Scattered<C> {
+        let frame = Onb::from_w(hit.normal);
+        let wo = frame.to_local(-ray.direction.unit());
+        if wo.z <= 0.0 {
+            return None;
+        }
+        let reflectance_o = reflectance(wo.z);
+        let p = reflectance_o.average().clamp(1e-3, 1.0 - 1e-3);
+
+        if random(rng) < p {
+            let smooth = self.distribution.is_smooth();
+            let m = if smooth {
+                Vec3::new(0.0, 0.0, 1.0)
+            } else {
+                self.distribution
+                    .sample_visible(wo, random(rng), random(rng))
+            };
+            let wi = m * (2.0 * wo.dot(&m)) - wo;
+            if wi.z <= 0.0 {
+                return None;
+            }
+            let direction = frame.local(wi.x, wi.y, wi.z);
+
+            if smooth {
+                return Some((
+                    Ray::new(hit.point, direction),
+                    reflectance_o * (1.0 / p),
+                    None,
+                ));
+            }
+            let shadowing = self.distribution.g(wo, wi) / self.distribution.g1(wo);
+            let (_, coat_pdf) = self.coat_eval(wo, wi);
+            // a specular base adds nothing here, the coat lobe alone is still
+            // weighted against light sampling like eval_layers counts it
+            let base_pdf = eval_base(direction).map_or(0.0, |(_, base_pdf)| base_pdf);
+            let pdf = Some(p * coat_pdf + (1.0 - p) * base_pdf);
+
+            return Some((
+                Ray::new(hit.point, direction),
+                reflectance(wo.dot(&m)) * (shadowing / p),
+                pdf,
+            ));
+        }
+
+        let (scattered, attenuation, base_pdf) = scatter_base(rng)?;
+        let wi = frame.to_local(scattered.direction.unit());
+        if wi.z <= 0.0 {
+            return None;
+        }
+        let one = C::splat(1.0);
+        let through = (one - reflectance_o)
+            * (one - reflectance(wi.z))
+            * to_color(self.transmittance(wo.z, wi.z));
+
+        let (_, coat_pdf) = self.coat_eval(wo, wi);
+        let pdf = base_pdf.map(|base_pdf| p * coat_pdf + (1.0 - p) * base_pdf);
+
+        Some((scattered, attenuation * through * (1.0 / (1.0 - p)), pdf))
+    }
+
+    fn eval_layers<C: Color>(
+        &self,
+        ray: &Ray,
+        hit: &Hit,
+        direction: Vec3,
+        reflectance: &dyn Fn(f64) -> C,
+        to_color: &dyn Fn(Vec3) -> C,
+        base: Option<(C, f64)>,
+    ) -> Option<(C, f64)> {
+        if base.is_none() && self.distribution.is_smooth() {
+            return None;
+        }
+        let frame = Onb::from_w(hit.normal);
+        let wo = frame.to_local(-ray.direction.unit());
+        let wi = frame.to_local(direction.unit());
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return Some((C::splat(0.0), 0.0));
+        }
+        let reflectance_o = reflectance(wo.z);
+        let p = reflectance_o.average().clamp(1e-3, 1.0 - 1e-3);
+
+        let (coat_f, coat_pdf) = self.coat_eval(wo, wi);
+        let mut f = C::splat(0.0);
+        let mut pdf = p * coat_pdf;
+        if coat_f > 0.0 {
+            f = reflectance((wo + wi).unit().dot(&wo)) * coat_f;
+        }
+        if let Some((base_f, base_pdf)) = base {
+            let one = C::splat(1.0);
+            let through = (one - reflectance_o)
+                * (one - reflectance(wi.z))
+                * to_color(self.transmittance(wo.z, wi.z));
+            f = f + base_f * through;
+            pdf += (1.0 - p) * base_pdf;
+        }
+
+        Some((f, pdf))
+    }
+}
+
+impl Material for Coated {
+    fn scatter(&self, ray: &Ray, hit: Hit, rng: &mut rand::rngs::ThreadRng) -> Option<(Ray, Vec3)> {
+        self.scatter_with_pdf(ray, hit, rng)
+            .map(|(scattered, attenuation, _)| (scattered, attenuation))
+    }
+
+    fn scatter_with_pdf(
+        &self,
+        ray: &Ray,
+        hit: Hit,
+        rng: &mut rand::rngs::ThreadRng,
+    ) -> Option<(Ray, Vec3, Option<f64>)> {
+        self.scatter_layers(
+            ray,
+            &hit,
+            rng,
+            &|cos| self.reflectance_rgb(cos),
+            &|rgb| rgb,
+            &mut |rng| self.base.scatter_with_pdf(ray, hit, rng),
+            &|direction| self.base.eval(ray, &hit, direction),
+        )
+    }
+
+    fn eval(&self, ray: &Ray, hit: &Hit, direction: Vec3) -> Option<(Vec3, f64)> {
+        let base = self.base.eval(ray, hit, direction);
+        self.eval_layers(
+            ray,
+            hit,
+            direction,
+            &|cos| self.reflectance_rgb(cos),
+            &|rgb| rgb,
+            base,
+        )
+    }
+
+    fn scatter_spectral(
+        &self,
+        ray: &Ray,
+        hit: Hit,
+        wavelengths: &mut Wavelengths,
+        rng: &mut rand::rngs::ThreadRng,
+    ) -> Option<(Ray, SampledSpectrum, Option<f64>)> {
+        let lambdas = *wavelengths;
+        self.scatter_layers(
+            ray,
+            &hit,
+            rng,
+            &|cos| self.reflectance_spectral(cos, &lambdas),
+            &|rgb| SampledSpectrum::from_rgb(rgb, &lambdas),
+            &mut |rng| self.base.scatter_spectral(ray, hit, wavelengths, rng),
+            &|direction| self.base.eval_spectral(ray, &hit, direction, &lambdas),
+        )
+    }
+
+    fn eval_spectral(
+        &self,
+        ray: &Ray,
+        hit: &Hit,
+        direction: Vec3,
+        wavelengths: &Wavelengths,
+    ) -> Option<(SampledSpectrum, f64)> {
+        let base = self.base.eval_spectral(ray, hit, direction, wavelengths);
+        self.eval_layers(
+            ray,
+            hit,
+            direction,
+            &|cos| self.reflectance_spectral(cos, wavelengths),
+            &|rgb| SampledSpectrum::from_rgb(rgb, wavelengths),
+            base,
+        )
+    }
+}
@@ -0,0 +1,138 @@
+use crate::hittable::Hit;
+use crate::material::Material;
+use crate::microfacet::{fresnel_conductor, Ggx};
+use crate::onb::Onb;
+use crate::random;
+use crate::ray::Ray;
+use crate::vec::Vec3;
+
+// rough metal: a ggx microfacet brdf with the fresnel term of a conductor,
+// eta and k are the real and imaginary index of refraction per rgb channel
+#[derive(Clone)]
+pub struct Conductor {
+    pub eta: Vec3,
+    pub k: Vec3,
+    pub distribution: Ggx,
+}
+
+impl Conductor {
+    pub fn new(eta: Vec3, k: Vec3, roughness: f64) -> Conductor {
+        Conductor {
+            eta,
+            k,
+            distribution: Ggx::new(roughness),
+        }
+    }
+
+    // roughness_u and roughness_v stretch the highlight along the surface's u
+    // direction, dpdu, and across it
+    pub fn anisotropic(eta: Vec3, k: Vec3, roughness_u: f64, roughness_v: f64) -> Conductor {
+        Conductor {
+            eta,
+            k,
+            distribution: Ggx::anisotropic(roughness_u, roughness_v),
+        }
+    }
+
+    // measured indices of refraction, averaged over the rgb primaries
+    pub fn gold(roughness: f64) -> Conductor {
+        Conductor::new(
+            Vec3::new(0.143119, 0.374957, 1.44248),
+            Vec3::new(3.98316, 2.38572, 1.60322),
+            roughness,
+        )
+    }
+
+    pub fn silver(roughness: f64) -> Conductor {
+        Conductor::new(
+            Vec3::new(0.155265, 0.116723, 0.138342),
+            Vec3::new(4.82835, 3.12225, 2.14696),
+            roughness,
+        )
+    }
+
+    pub fn copper(roughness: f64) -> Conductor {
+        Conductor::new(
+            Vec3::new(0.200438, 0.924033, 1.10221),
+            Vec3::new(3.91295, 2.45285, 2.14219),
+            roughness,
+        )
+    }
+
+    pub fn aluminum(roughness: f64) -> Conductor {
+        Conductor::new(
+            Vec3::new(1.65746, 0.880369, 0.521229),
+            Vec3::new(9.22387, 6.26952, 4.837),
+            roughness,
+        )
+    }
+
+    pub fn chromium(roughness: f64) -> Conductor {
+        Conductor::new(
+            Vec3::new(3.1071, 3.18124, 2.3230),
+            Vec3::new(3.3314, 3.3291, 3.1350),
+            roughness,
+        )
+    }
+
+    pub fn iron(roughness: f64) -> Conductor {
+        Conductor::new(
+            Vec3::new(2.9114, 2.9497, 2.5845),
+            Vec3::new(3.0893, 2.9318, 2.7670),
+            roughness,
+        )
+    }
+}
+
+impl Material for Conductor {
+    fn scatter(&self, ray: &Ray, hit: Hit, rng: &mut rand::rngs::ThreadRng) -> Option<(Ray, Vec3)> {
+        let frame = Onb::from_w_tangent(hit.normal, hit.dpdu);
+        let wo = frame.to_local(-ray.direction.unit());
+        if wo.z <= 0.0 {
+            return None;
+        }
+
+        if self.distribution.is_smooth() {
+            let attenuation = fresnel_conductor(wo.z, self.eta, self.k);
+            let direction = frame.local(-wo.x, -wo.y, wo.z);
+            return Some((Ray::new(hit.point, direction), attenuation));
+        }
+
+        let m = self
+            .distribution
+            .sample_visible(wo, random(rng), random(rng));
+        let wi = m * (2.0 * wo.dot(&m)) - wo;
+        if wi.z <= 0.0 {
+            return None;
+        }
+
+        // with visible normal sampling everything but fresnel and shadowing cancels
+        let attenuation = fresnel_conductor(wo.dot(&m), self.eta, self.k)
+            * (self.distribution.g(wo, wi) / self.distribution.g1(wo));
+
+        Some((
+            Ray::new(hit.point, frame.local(wi.x, wi.y, wi.z)),
+            attenuation,
+        ))
+    }
+
+    fn eval(&self, ray: &Ray, hit: &Hit, direction: Vec3) -> Option<(Vec3, f64)> {
+        if self.distribution.is_smooth() {
+            return None;
+        }
+        let frame = Onb::from_w_tangent(hit.normal, hit.dpdu);
+        let wo = frame.to_local(-ray.direction.unit());
+        let wi = frame.to_local(direction.unit());
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return Some((Vec3::new(0.0, 0.0, 0.0), 0.0));
+        }
+
+        let m = (wo + wi).unit();
+        let d = self.distribution.d(m);
+        let f = fresnel_conductor(wo.dot(&m), self.eta, self.k)
+            * (d * self.distribution.g(wo, wi) / (4.0 * wo.z));
+        let pdf = self.distribution.g1(wo) * d / (4.0 * wo.z);
+
+        Some((f, pdf))
+    }
+}
@@ -0,0 +1,176 @@
+use crate::hittable::{Hit, Hittable};
+use crate::material::Material;
+use crate::random;
+use crate::ray::Ray;
+use crate::vec::Vec3;
+
+// axis aligned box from min to max
+pub struct Cuboid {
+    pub min: Vec3,
+    pub max: Vec3,
+    pub material: Option<Box<dyn Material>>,
+}
+
+impl Cuboid {
+    pub fn new(min: Vec3, max: Vec3, material: Option<Box<dyn Material>>) -> Cuboid {
+        Cuboid { min, max, material }
+    }
+
+    // each face is mapped to the whole of uv, with dpdu x dpdv pointing out
+    // like a sphere's, so textures and normal maps sit upright on the sides
+    fn parameterize(&self, hit: &mut Hit, outward_normal: Vec3) {
+        let p = hit.point;
+        let size = self.max - self.min;
+        let fraction = |x: f64, min: f64, extent: f64| {
+            if extent > 0.0 {
+                (x - min) / extent
+            } else {
+                0.0
+            }
+        };
+        let (x, y, z) = (
+            fraction(p.x, self.min.x, size.x),
+            fraction(p.y, self.min.y, size.y),
+            fraction(p.z, self.min.z, size.z),
+        );
+        let along_x = Vec3::new(size.x, 0.0, 0.0);
+        let along_y = Vec3::new(0.0, size.y, 0.0);
+        let along_z = Vec3::new(0.0, 0.0, size.z);
+
+        let n = outward_normal;
+        let (u, v, dpdu, dpdv) = if n.x > 0.0 {
+            (1.0 - z, y, -along_z, along_y)
+        } else if n.x < 0.0 {
+            (z, y, along_z, along_y)
+        } else if n.y > 0.0 {
+            (x, 1.0 - z, along_x, -along_z)
+        } else if n.y < 0.0 {
+            (x, z, along_x, along_z)
+        } else if n.z > 0.0 {
+            (x, y, along_x, along_y)
+        } else {
+            (1.0 - x, y, -along_x, along_y)
+        };
+        hit.u = u;
+        hit.v = v;
+        hit.dpdu = dpdu;
+        hit.dpdv = dpdv;
+    }
+}
+
+impl Hittable for Cuboid {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Hit<'_>> {
+        // slab test, keeping the axis each of the two crossings happened on
+        let mut near = (f64::NEG_INFINITY, 0);
+        let mut far = (f64::INFINITY, 0);
+        let axes = [
+            (ray.origin.x, ray.direction.x, self.min.x, self.max.x),
+            (ray.origin.y, ray.direction.y, self.min.y, self.max.y),
+            (ray.origin.z, ray.direction.z, self.min.z, self.max.z),
+        ];
+        for (axis, (origin, direction, min, max)) in axes.iter().enumerate() {
+            if *direction == 0.0 {
+                if origin < min || origin > max {
+                    return None;
+                }
+                continue;
+            }
+            let t0 = (min - origin) / direction;
+            let t1 = (max - origin) / direction;
+            let (t0, t1) = if t0 < t1 { (t0, t1) } else { (t1, t0) };
+            if t0 > near.0 {
+                near = (t0, axis);
+            }
+            if t1 < far.0 {
+                far = (t1, axis);
+            }
+        }
+        if near.0 > far.0 {
+            return None;
+        }
+
+        let (root, axis) = if near.0 >= t_min && near.0 <= t_max {
+            near
+        } else if far.0 >= t_min && far.0 <= t_max {
+            far
+        } else {
+            return None;
+        };
+
+        let point = ray.at(root);
+        let center = (self.min + self.max) * 0.5;
+        let outward_normal = match axis {
+            0 => Vec3::new((point.x - center.x).signum(), 0.0, 0.0),
+            1 => Vec3::new(0.0, (point.y - center.y).signum(), 0.0),
+            _ => Vec3::new(0.0, 0.0, (point.z - center.z).signum()),
+        };
+
+        let front_face = ray.direction.dot(&outward_normal) < 0.0;
+        let normal = if front_face {
+            outward_normal
+        } else {
+            -outward_normal
+        };
+
+        let mut hit = Hit::new(root, point, normal, front_face, self.material.as_deref());
+        self.parameterize(&mut hit, outward_normal);
+        Some(hit)
+    }
+
+    fn sample(&self, rng: &mut rand::rngs::ThreadRng) -> Option<Hit<'_>> {
+        let size = self.max - self.min;
+        let faces = [size.y * size.z, size.x * size.z, size.x * size.y];
+        let total = faces[0] + faces[1] + faces[2];
+        if total <= 0.0 {
+            return None;
+        }
+
+        // a face pair by area, then one of the two sides
+        let pick = random(rng) * total;
+        let axis = if pick < faces[0] {
+            0
+        } else if pick < faces[0] + faces[1] {
+            1
+        } else {
+            2
+        };
+        let side = if random(rng) < 0.5 { -1.0 } else { 1.0 };
+
+        let mut point = Vec3::new(
+            self.min.x + random(rng) * size.x,
+            self.min.y + random(rng) * size.y,
+            self.min.z + random(rng) * size.z,
+        );
+        let normal = match axis {
+            0 => {
+                point.x = if side > 0.0 { self.max.x } else { self.min.x };
+                Vec3::new(side, 0.0, 0.0)
+            }
+            1 => {
+                point.y = if side > 0.0 { self.max.y } else { self.min.y };
+                Vec3::new(0.0, side, 0.0)
+            }
+            _ => {
+                point.z = if side > 0.0 { self.max.z } else { self.min.z };
+                Vec3::new(0.0, 0.0, side)
+            }
+        };
+
+        let mut hit = Hit::new(0.0, point, normal, true, self.material.as_deref());
+        self.parameterize(&mut hit, normal);
+        Some(hit)
+    }
+
+    fn area(&self) -> f64 {
+        let size = self.max - self.min;
+        2.0 * (size.y * size.z + size.x * size.z + size.x * size.y)
+    }
+
+    fn bounding_box(&self) -> Option<(Vec3, Vec3)> {
+        Some((self.min, self.max))
+    }
+
+    fn material(&self) -> Option<&dyn Material> {
+        self.material.as_deref()
+    }
+}
@@ -0,0 +1,97 @@
+use crate::hittable::Hit;
+use crate::material::Material;
+use crate::medium::Medium;
+use crate::ray::Ray;
+use crate::spectrum::{Color, SampledSpectrum, Wavelengths};
+use crate::texture::Texture;
+use crate::vec::Vec3;
+
+// material with holes cut out of it by an alpha texture, for leaves and decals.
+// the integrator lets rays through wherever the surface is transparent
+pub struct Cutout {
+    pub material: Box<dyn Material>,
+    pub alpha: Box<dyn Texture>,
+}
+
+impl Cutout {
+    pub fn new(material: Box<dyn Material>, alpha: Box<dyn Texture>) -> Cutout {
+        Cutout { material, alpha }
+    }
+}
+
+impl Material for Cutout {
+    fn scatter(&self, ray: &Ray, hit: Hit, rng: &mut rand::rngs::ThreadRng) -> Option<(Ray, Vec3)> {
+        self.material.scatter(ray, hit, rng)
+    }
+
+    fn eval(&self, ray: &Ray, hit: &Hit, direction: Vec3) -> Option<(Vec3, f64)> {
+        self.material.eval(ray, hit, direction)
+    }
+
+    fn scatter_with_pdf(
+        &self,
+        ray: &Ray,
+        hit: Hit,
+        rng: &mut rand::rngs::ThreadRng,
+    ) -> Option<(Ray, Vec3, Option<f64>)> {
+        self.material.scatter_with_pdf(ray, hit, rng)
+    }
+
+    fn scatter_sampled(
+        &self,
+        ray: &Ray,
+        hit: Hit,
+        u_lobe: f64,
+        u: (f64, f64),
+        rng: &mut rand::rngs::ThreadRng,
+    ) -> Option<(Ray, Vec3, Option<f64>)> {
+        self.material.scatter_sampled(ray, hit, u_lobe, u, rng)
+    }
+
+    fn scatter_spectral(
+        &self,
+        ray: &Ray,
+        hit: Hit,
+        wavelengths: &mut Wavelengths,
+        rng: &mut rand::rngs::ThreadRng,
+    ) -> Option<(Ray, SampledSpectrum, Option<f64>)> {
+        self.material.scatter_spectral(ray, hit, wavelengths, rng)
+    }
+
+    fn scatter_spectral_sampled(
+        &self,
+        ray: &Ray,
+        hit: Hit,
+        wavelengths: &mut Wavelengths,
+        u_lobe: f64,
+        u: (f64, f64),
+        rng: &mut rand::rngs::ThreadRng,
+    ) -> Option<(Ray, SampledSpectrum, Option<f64>)> {
+        self.material
+            .scatter_spectral_sampled(ray, hit, wavelengths, u_lobe, u, rng)
+    }
+
+    fn eval_spectral(
+        &self,
+        ray: &Ray,
+        hit: &Hit,
+        direction: Vec3,
+        wavelengths: &Wavelengths,
+    ) -> Option<(SampledSpectrum, f64)> {
+        self.material
+            .eval_spectral(ray, hit, direction, wavelengths)
+    }
+
+    fn emitted(&self, hit: &Hit) -> Vec3 {
+        self.material.emitted(hit)
+    }
+
+    fn interior(&self) -> Option<&dyn Medium> {
+        self.material.interior()
+    }
+
+    fn alpha(&self, hit: &Hit) -> f64 {
+        let alpha = self.alpha.value(hit.u, hit.v, hit.point).average();
+        alpha.clamp(0.0, 1.0) * self.material.alpha(hit)
+    }
+}
@@ -1,5 +1,6 @@
 use crate::hittable::Hit;
 use crate::material::Material;
+use crate::medium::{Absorbing, Medium};
 use crate::random;
 use crate::ray::Ray;
 use crate::vec::Vec3;
@@ -24,6 +25,7 @@ pub struct Dielectric10_3 {
 #[derive(Clone)]
 pub struct Dielectric10_4 {
     pub index_of_refraction: f64,
+    pub absorption: Option<Absorbing>,
 }
 
 impl Dielectric10_2 {
@@ -101,6 +103,15 @@ impl Dielectric10_4 {
     pub fn new(index_of_refraction: f64) -> Dielectric10_4 {
         Dielectric10_4 {
             index_of_refraction,
+            absorption: None,
+        }
+    }
+
+    // tinted glass, the color deepens with the distance light travels inside
+    pub fn colored(index_of_refraction: f64, absorption: Absorbing) -> Dielectric10_4 {
+        Dielectric10_4 {
+            index_of_refraction,
+            absorption: Some(absorption),
         }
     }
 }
@@ -131,4 +142,8 @@ impl Material for Dielectric10_4 {
 
         Some((scattered, attenuation))
     }
+
+    fn interior(&self) -> Option<&dyn Medium> {
+        self.absorption.as_ref().map(|a| a as &dyn Medium)
+    }
 }
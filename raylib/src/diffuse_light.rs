@@ -0,0 +1,35 @@
+use crate::hittable::Hit;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vec::Vec3;
+
+// surface that glows with the same radiance in every direction from its front
+// face and doesn't reflect anything
+pub struct DiffuseLight {
+    pub emit: Vec3,
+}
+
+impl DiffuseLight {
+    pub fn new(emit: Vec3) -> DiffuseLight {
+        DiffuseLight { emit }
+    }
+}
+
+impl Material for DiffuseLight {
+    fn scatter(
+        &self,
+        _ray: &Ray,
+        _hit: Hit,
+        _rng: &mut rand::rngs::ThreadRng,
+    ) -> Option<(Ray, Vec3)> {
+        None
+    }
+
+    fn emitted(&self, hit: &Hit) -> Vec3 {
+        if hit.front_face {
+            self.emit
+        } else {
+            Vec3::new(0.0, 0.0, 0.0)
+        }
+    }
+}
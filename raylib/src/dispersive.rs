@@ -0,0 +1,86 @@
+use crate::hittable::Hit;
+use crate::material::Material;
+use crate::medium::{Absorbing, Medium};
+use crate::microfacet::fresnel_dielectric;
+use crate::random;
+use crate::ray::Ray;
+use crate::spectrum::{Ior, SampledSpectrum, Wavelengths, LAMBDA_D};
+use crate::vec::Vec3;
+
+// smooth glass whose index of refraction follows a dispersion curve, so prisms and
+// gems split white light when rendered spectrally. rgb renders use the d line index
+#[derive(Clone)]
+pub struct Dispersive {
+    pub ior: Ior,
+    pub absorption: Option<Absorbing>,
+}
+
+impl Dispersive {
+    pub fn new(ior: Ior) -> Dispersive {
+        Dispersive {
+            ior,
+            absorption: None,
+        }
+    }
+
+    pub fn colored(ior: Ior, absorption: Absorbing) -> Dispersive {
+        Dispersive {
+            ior,
+            absorption: Some(absorption),
+        }
+    }
+
+    fn refract_or_reflect(
+        &self,
+        ray: &Ray,
+        hit: &Hit,
+        index_of_refraction: f64,
+        rng: &mut rand::rngs::ThreadRng,
+    ) -> Ray {
+        let eta = if hit.front_face {
+            index_of_refraction
+        } else {
+            1.0 / index_of_refraction
+        };
+        let unit_direction = ray.direction.unit();
+        let cos_theta = (-unit_direction).dot(&hit.normal).min(1.0);
+
+        // total internal reflection has a reflectance of 1
+        let direction = if fresnel_dielectric(cos_theta, eta) > random(rng) {
+            unit_direction.reflect(hit.normal)
+        } else {
+            unit_direction.refract(hit.normal, 1.0 / eta)
+        };
+
+        Ray::new(hit.point, direction)
+    }
+}
+
+impl Material for Dispersive {
+    fn scatter(&self, ray: &Ray, hit: Hit, rng: &mut rand::rngs::ThreadRng) -> Option<(Ray, Vec3)> {
+        let scattered = self.refract_or_reflect(ray, &hit, self.ior.at(LAMBDA_D), rng);
+
+        Some((scattered, Vec3::new(1.0, 1.0, 1.0)))
+    }
+
+    fn scatter_spectral(
+        &self,
+        ray: &Ray,
+        hit: Hit,
+        wavelengths: &mut Wavelengths,
+        rng: &mut rand::rngs::ThreadRng,
+    ) -> Option<(Ray, SampledSpectrum, Option<f64>)> {
+        // every wavelength would refract differently, only the hero keeps going
+        if self.ior.is_dispersive() {
+            wavelengths.terminate_secondary();
+        }
+        let index_of_refraction = self.ior.at(wavelengths.hero());
+        let scattered = self.refract_or_reflect(ray, &hit, index_of_refraction, rng);
+
+        Some((scattered, SampledSpectrum::new(1.0), None))
+    }
+
+    fn interior(&self) -> Option<&dyn Medium> {
+        self.absorption.as_ref().map(|a| a as &dyn Medium)
+    }
+}
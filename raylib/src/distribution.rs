@@ -0,0 +1,170 @@
+// piecewise-constant distributions, used to importance sample tabulated
+// functions such as the texels of an environment map
+pub struct Distribution1D {
+    pub func: std::vec::Vec<f64>,
+    pub cdf: std::vec::Vec<f64>,
+    pub integral: f64,
+}
+
+impl Distribution1D {
+    pub fn new(func: &[f64]) -> Distribution1D {
+        if func.is_empty() {
+            panic!("error distribution needs at least one value");
+        }
+        let n = func.len();
+        let func: std::vec::Vec<f64> = func.iter().map(|f| f.abs()).collect();
+
+        let mut cdf = vec![0.0; n + 1];
+        for i in 1..=n {
+            cdf[i] = cdf[i - 1] + func[i - 1] / n as f64;
+        }
+
+        // an all black function is sampled uniformly
+        let integral = cdf[n];
+        if integral == 0.0 {
+            for (i, c) in cdf.iter_mut().enumerate().skip(1) {
+                *c = i as f64 / n as f64;
+            }
+        } else {
+            for c in cdf.iter_mut().skip(1) {
+                *c /= integral;
+            }
+        }
+
+        Distribution1D {
+            func,
+            cdf,
+            integral,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.func.len()
+    }
+
+    // returns the sampled position in [0, 1), its pdf and the segment it fell in
+    pub fn sample_continuous(&self, u: f64) -> (f64, f64, usize) {
+        // last index whose cdf is <= u
+        let offset = self
+            .cdf
+            .partition_point(|c| *c <= u)
+            .saturating_sub(1)
+            .min(self.count() - 1);
+
+        let mut du = u - self.cdf[offset];
+        let width = self.cdf[offset + 1] - self.cdf[offset];
+        if width > 0.0 {
+            du /= width;
+        }
+
+        let pdf = if self.integral > 0.0 {
+            self.func[offset] / self.integral
+        } else {
+            1.0
+        };
+
+        ((offset as f64 + du) / self.count() as f64, pdf, offset)
+    }
+
+    pub fn pdf(&self, x: f64) -> f64 {
+        if self.integral == 0.0 {
+            return 1.0;
+        }
+        let offset = ((x * self.count() as f64) as usize).min(self.count() - 1);
+
+        self.func[offset] / self.integral
+    }
+}
+
+// marginal distribution over rows (v) and a conditional distribution per row (u)
+pub struct Distribution2D {
+    pub conditional: std::vec::Vec<Distribution1D>,
+    pub marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    pub fn new(func: &[f64], width: usize, height: usize) -> Distribution2D {
+        let conditional: std::vec::Vec<Distribution1D> = (0..height)
+            .map(|row| Distribution1D::new(&func[row * width..(row + 1) * width]))
+            .collect();
+        let integrals: std::vec::Vec<f64> = conditional.iter().map(|c| c.integral).collect();
+
+        Distribution2D {
+            conditional,
+            marginal: Distribution1D::new(&integrals),
+        }
+    }
+
+    // returns (u, v) in [0, 1)^2 and the pdf with respect to that domain
+    pub fn sample_continuous(&self, u0: f64, u1: f64) -> (f64, f64, f64) {
+        let (v, pdf_v, row) = self.marginal.sample_continuous(u1);
+        let (u, pdf_u, _) = self.conditional[row].sample_continuous(u0);
+
+        (u, v, pdf_u * pdf_v)
+    }
+
+    pub fn pdf(&self, u: f64, v: f64) -> f64 {
+        let rows = self.conditional.len();
+        let row = ((v * rows as f64) as usize).min(rows - 1);
+
+        self.marginal.pdf(v) * self.conditional[row].pdf(u)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pdf_1d_integrates_to_one_and_matches_sample() {
+        let distribution = Distribution1D::new(&[1.0, 0.0, 3.0, 2.0]);
+        let n = 1000;
+        let integral: f64 = (0..n)
+            .map(|i| distribution.pdf((i as f64 + 0.5) / n as f64) / n as f64)
+            .sum();
+        assert!((integral - 1.0).abs() < 1e-9);
+
+        let mut counts = [0; 4];
+        for i in 0..n {
+            let (x, pdf, offset) = distribution.sample_continuous((i as f64 + 0.5) / n as f64);
+            assert!((0.0..1.0).contains(&x));
+            assert_eq!(offset, (x * 4.0) as usize);
+            assert!((pdf - distribution.pdf(x)).abs() < 1e-12);
+            counts[offset] += 1;
+        }
+        assert_eq!(counts, [167, 0, 500, 333]);
+    }
+
+    #[test]
+    fn black_1d_is_uniform() {
+        let distribution = Distribution1D::new(&[0.0, 0.0]);
+        let (x, pdf, _) = distribution.sample_continuous(0.75);
+        assert!((x - 0.75).abs() < 1e-12);
+        assert_eq!(pdf, 1.0);
+        assert_eq!(distribution.pdf(0.2), 1.0);
+    }
+
+    #[test]
+    fn pdf_2d_integrates_to_one_and_matches_sample() {
+        let func = [
+            1.0, 2.0, 0.0, 4.0, //
+            0.5, 0.0, 0.0, 1.5, //
+            3.0, 1.0, 2.0, 0.0,
+        ];
+        let distribution = Distribution2D::new(&func, 4, 3);
+        // a multiple of both sizes, so the midpoints split every cell evenly
+        let n = 240;
+        let mut integral = 0.0;
+        for i in 0..n {
+            for j in 0..n {
+                let (u, v) = ((i as f64 + 0.5) / n as f64, (j as f64 + 0.5) / n as f64);
+                integral += distribution.pdf(u, v) / (n * n) as f64;
+
+                let (su, sv, pdf) = distribution.sample_continuous(u, v);
+                assert!(pdf > 0.0);
+                assert!((pdf - distribution.pdf(su, sv)).abs() < 1e-9);
+            }
+        }
+        assert!((integral - 1.0).abs() < 1e-9);
+    }
+}
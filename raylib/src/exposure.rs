@@ -0,0 +1,38 @@
+use crate::image::Image;
+
+// photographic exposure (iso 2720 and 12232). pixel values are radiance in
+// cd/m^2 scaled so that the brightest value the sensor records comes out as 1
+
+// reflected light meter calibration constant
+const METER_CALIBRATION: f64 = 12.5;
+
+// exposure value at iso 100 of the f-number, shutter time in seconds and iso
+pub fn ev100(f_number: f64, shutter: f64, iso: f64) -> f64 {
+    (f_number * f_number / shutter * 100.0 / iso).log2()
+}
+
+// scale from radiance to pixel values at the exposure value, for a sensor that
+// saturates at 1.2 * 2^ev100 cd/m^2 (saturation based speed)
+pub fn exposure_scale(ev100: f64) -> f64 {
+    1.0 / (1.2 * 2.0_f64.powf(ev100))
+}
+
+// exposure value a light meter would pick for the image, from the log average of
+// its luminance
+pub fn auto_ev100(image: &Image) -> f64 {
+    let mut sum = 0.0;
+    let mut count = 0.0;
+    for pixel in image.pixels.iter() {
+        let luminance = pixel.luminance();
+        if luminance.is_finite() {
+            sum += (luminance + 1e-4).ln();
+            count += 1.0;
+        }
+    }
+    if count == 0.0 {
+        return 0.0;
+    }
+    let average = (sum / count).exp();
+
+    (average * 100.0 / METER_CALIBRATION).log2()
+}
@@ -0,0 +1,180 @@
+use crate::vec::Vec3;
+
+use std::fs;
+use std::path::Path;
+
+// Radiance .hdr (RGBE) image, stored top row first
+pub struct Hdr {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: std::vec::Vec<Vec3>,
+}
+
+fn rgbe_to_vec(rgbe: &[u8]) -> Vec3 {
+    if rgbe[3] == 0 {
+        return Vec3::new(0.0, 0.0, 0.0);
+    }
+    let scale = 2.0_f64.powi(rgbe[3] as i32 - (128 + 8));
+
+    Vec3::new(
+        rgbe[0] as f64 * scale,
+        rgbe[1] as f64 * scale,
+        rgbe[2] as f64 * scale,
+    )
+}
+
+fn read_line(bytes: &[u8], pos: &mut usize) -> String {
+    let start = *pos;
+    while *pos < bytes.len() && bytes[*pos] != b'\n' {
+        *pos += 1;
+    }
+    let line = String::from_utf8_lossy(&bytes[start..*pos]).to_string();
+    *pos += 1;
+
+    line
+}
+
+fn next_byte(bytes: &[u8], pos: &mut usize) -> u8 {
+    if *pos >= bytes.len() {
+        panic!("error unexpected end of hdr data");
+    }
+    *pos += 1;
+
+    bytes[*pos - 1]
+}
+
+// new style run length encoding: each of the four channels is stored separately
+fn read_rle_scanline(bytes: &[u8], pos: &mut usize, width: usize) -> std::vec::Vec<u8> {
+    let mut scanline = vec![0u8; width * 4];
+    for channel in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let count = next_byte(bytes, pos) as usize;
+            // runs are over 128, literal spans up to it
+            let run_length = if count > 128 { count - 128 } else { count };
+            if run_length == 0 || x + run_length > width {
+                panic!("error bad hdr scanline length");
+            }
+            if count > 128 {
+                let value = next_byte(bytes, pos);
+                for _ in 0..run_length {
+                    scanline[x * 4 + channel] = value;
+                    x += 1;
+                }
+            } else {
+                for _ in 0..run_length {
+                    scanline[x * 4 + channel] = next_byte(bytes, pos);
+                    x += 1;
+                }
+            }
+        }
+    }
+    scanline
+}
+
+impl Hdr {
+    pub fn open(path: &str) -> Hdr {
+        let bytes = match fs::read(Path::new(path)) {
+            Err(err) => panic!("error {}", err),
+            Ok(bytes) => bytes,
+        };
+        let mut pos = 0;
+
+        let magic = read_line(&bytes, &mut pos);
+        if !magic.starts_with("#?") {
+            panic!("error {} is not a radiance hdr file", path);
+        }
+        loop {
+            let line = read_line(&bytes, &mut pos);
+            if line.is_empty() {
+                break;
+            }
+            if line.starts_with("FORMAT=") && line != "FORMAT=32-bit_rle_rgbe" {
+                panic!("error unsupported hdr {}", line);
+            }
+        }
+
+        // only the standard orientation is supported: -Y height +X width
+        let resolution = read_line(&bytes, &mut pos);
+        let parts: std::vec::Vec<&str> = resolution.split_whitespace().collect();
+        if parts.len() != 4 || parts[0] != "-Y" || parts[2] != "+X" {
+            panic!("error unsupported hdr resolution {}", resolution);
+        }
+        let height: usize = match parts[1].parse() {
+            Err(err) => panic!("error {}", err),
+            Ok(h) => h,
+        };
+        let width: usize = match parts[3].parse() {
+            Err(err) => panic!("error {}", err),
+            Ok(w) => w,
+        };
+
+        let mut pixels = std::vec::Vec::with_capacity(width * height);
+        for _ in 0..height {
+            let is_rle = (8..0x8000).contains(&width)
+                && pos + 4 <= bytes.len()
+                && bytes[pos] == 2
+                && bytes[pos + 1] == 2
+                && bytes[pos + 2] & 0x80 == 0;
+
+            let scanline = if is_rle {
+                pos += 4;
+                read_rle_scanline(&bytes, &mut pos, width)
+            } else {
+                if pos + width * 4 > bytes.len() {
+                    panic!("error unexpected end of hdr data");
+                }
+                pos += width * 4;
+                bytes[pos - width * 4..pos].to_vec()
+            };
+            for rgbe in scanline.chunks(4) {
+                pixels.push(rgbe_to_vec(rgbe));
+            }
+        }
+
+        Hdr {
+            width,
+            height,
+            pixels,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_run_length_encoded_scanlines() {
+        let width = 10;
+        let pixels: std::vec::Vec<[u8; 4]> = (0..width)
+            .map(|x| [if x < 6 { 200 } else { 10 * x as u8 }, x as u8, 64, 129])
+            .collect();
+
+        let mut bytes = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 2 +X 10\n".to_vec();
+        for _ in 0..2 {
+            bytes.extend_from_slice(&[2, 2, 0, width as u8]);
+            // red as a run of six and a literal span of four
+            bytes.extend_from_slice(&[128 + 6, 200, 4, 60, 70, 80, 90]);
+            // green as one literal span
+            bytes.push(width as u8);
+            bytes.extend((0..width).map(|x| x as u8));
+            // blue and the exponent as runs over the whole row
+            bytes.extend_from_slice(&[128 + width as u8, 64, 128 + width as u8, 129]);
+        }
+        let path = std::env::temp_dir().join(format!("raylib_rle_{}.hdr", std::process::id()));
+        fs::write(&path, &bytes).unwrap();
+        let hdr = Hdr::open(path.to_str().unwrap());
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!((hdr.width, hdr.height), (width, 2));
+        for (i, pixel) in hdr.pixels.iter().enumerate() {
+            let expected = rgbe_to_vec(&pixels[i % width]);
+            assert_eq!(
+                (pixel.x, pixel.y, pixel.z),
+                (expected.x, expected.y, expected.z)
+            );
+        }
+        assert_eq!(hdr.pixels[0].x, 200.0 / 128.0);
+    }
+}
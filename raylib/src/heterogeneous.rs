@@ -0,0 +1,129 @@
+use crate::medium::{HenyeyGreenstein, Medium, MediumSample};
+use crate::random;
+use crate::ray::Ray;
+use crate::vec::Vec3;
+use crate::voxel::VoxelGrid;
+
+// medium whose density comes from a voxel grid stretched over the box from min to
+// max, for clouds, smoke and fire. extinction is the same for every channel and
+// the albedo colors what scatters. free paths are sampled with delta tracking and
+// transmittance estimated with ratio tracking (novák et al. 2014), both against
+// the majorant set by the densest voxel
+pub struct Heterogeneous {
+    pub density: VoxelGrid,
+    pub min: Vec3,
+    pub max: Vec3,
+    // extinction coefficient where the density is 1
+    pub sigma_t: f64,
+    pub albedo: Vec3,
+    pub phase: HenyeyGreenstein,
+    // emitted radiance where the grid is 1, only the absorbed part (1 - albedo)
+    // of the medium emits
+    pub emission: Option<VoxelGrid>,
+    pub emission_color: Vec3,
+}
+
+impl Heterogeneous {
+    pub fn new(
+        density: VoxelGrid,
+        min: Vec3,
+        max: Vec3,
+        sigma_t: f64,
+        albedo: Vec3,
+        g: f64,
+    ) -> Heterogeneous {
+        Heterogeneous {
+            density,
+            min,
+            max,
+            sigma_t,
+            albedo,
+            phase: HenyeyGreenstein::new(g),
+            emission: None,
+            emission_color: Vec3::new(0.0, 0.0, 0.0),
+        }
+    }
+
+    fn grid_point(&self, point: Vec3) -> Vec3 {
+        let size = self.max - self.min;
+        Vec3::new(
+            (point.x - self.min.x) / size.x,
+            (point.y - self.min.y) / size.y,
+            (point.z - self.min.z) / size.z,
+        )
+    }
+
+    fn majorant(&self) -> f64 {
+        self.sigma_t * self.density.max
+    }
+}
+
+impl Medium for Heterogeneous {
+    fn transmittance(&self, ray: &Ray, distance: f64, rng: &mut rand::rngs::ThreadRng) -> Vec3 {
+        let majorant = self.majorant();
+        if majorant <= 0.0 {
+            return Vec3::new(1.0, 1.0, 1.0);
+        }
+
+        let mut transmittance = 1.0;
+        let mut t = 0.0;
+        loop {
+            t -= (1.0 - random(rng)).ln() / majorant;
+            if t >= distance {
+                break;
+            }
+            let density = self.density.lookup(self.grid_point(ray.at(t)));
+            transmittance *= 1.0 - density / self.density.max;
+
+            // russian roulette once little is left, long rays through thick media
+            // would otherwise take many steps for nothing
+            if transmittance < 0.1 {
+                if random(rng) < 0.5 {
+                    return Vec3::new(0.0, 0.0, 0.0);
+                }
+                transmittance *= 2.0;
+            }
+        }
+        Vec3::new(transmittance, transmittance, transmittance)
+    }
+
+    fn sample(&self, ray: &Ray, distance: f64, rng: &mut rand::rngs::ThreadRng) -> MediumSample {
+        let majorant = self.majorant();
+        if majorant <= 0.0 {
+            return MediumSample::passed(Vec3::new(1.0, 1.0, 1.0));
+        }
+
+        let mut t = 0.0;
+        loop {
+            t -= (1.0 - random(rng)).ln() / majorant;
+            if t >= distance {
+                return MediumSample::passed(Vec3::new(1.0, 1.0, 1.0));
+            }
+            let point = self.grid_point(ray.at(t));
+            let density = self.density.lookup(point);
+
+            // a real collision rather than a null one
+            if random(rng) * self.density.max < density {
+                let one = Vec3::new(1.0, 1.0, 1.0);
+                let emission = match &self.emission {
+                    Some(grid) => self.emission_color * (one - self.albedo) * grid.lookup(point),
+                    None => Vec3::new(0.0, 0.0, 0.0),
+                };
+
+                return MediumSample {
+                    scattered_at: Some(t),
+                    weight: self.albedo,
+                    emission,
+                };
+            }
+        }
+    }
+
+    fn phase(&self, direction: Vec3, scattered: Vec3) -> f64 {
+        self.phase.eval(direction, scattered)
+    }
+
+    fn sample_phase(&self, direction: Vec3, rng: &mut rand::rngs::ThreadRng) -> (Vec3, f64) {
+        self.phase.sample(direction, rng)
+    }
+}
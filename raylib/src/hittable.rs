@@ -2,16 +2,47 @@ use crate::material::Material;
 use crate::ray::Ray;
 use crate::vec::Vec3;
 
+#[derive(Clone, Copy)]
 pub struct Hit<'world> {
     pub t: f64,
     pub point: Vec3,
+    // shading normal, facing the incoming ray like the geometric one
     pub normal: Vec3,
+    // normal of the actual surface, which decides what side a ray is on
+    pub geometric_normal: Vec3,
     pub front_face: bool,
+    // surface coordinates textures are looked up with
+    pub u: f64,
+    pub v: f64,
+    // change of the point along u and v, zero when the surface has no coordinates
+    pub dpdu: Vec3,
+    pub dpdv: Vec3,
     pub material: Option<&'world dyn Material>,
+    // index of the object in the world's list
+    pub object: usize,
 }
 
 pub trait Hittable {
     fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Hit>;
+
+    // uniformly picks a point on the surface, as a front face hit with the normal
+    // pointing out. emissive shapes need it to be sampled as lights
+    fn sample(&self, _rng: &mut rand::rngs::ThreadRng) -> Option<Hit<'_>> {
+        None
+    }
+
+    fn area(&self) -> f64 {
+        0.0
+    }
+
+    // corners of an axis aligned box around the shape, None when it's unbounded
+    fn bounding_box(&self) -> Option<(Vec3, Vec3)> {
+        None
+    }
+
+    fn material(&self) -> Option<&dyn Material> {
+        None
+    }
 }
 
 impl<'world> Hit<'world> {
@@ -26,8 +57,14 @@ impl<'world> Hit<'world> {
             t,
             point,
             normal,
+            geometric_normal: normal,
             front_face,
+            u: 0.0,
+            v: 0.0,
+            dpdu: Vec3::new(0.0, 0.0, 0.0),
+            dpdv: Vec3::new(0.0, 0.0, 0.0),
             material,
+            object: 0,
         }
     }
 }
@@ -47,10 +84,11 @@ impl Hittables {
         let mut closest = t_max;
         let mut current_point = None;
 
-        for obj in self.list.iter() {
+        for (i, obj) in self.list.iter().enumerate() {
             match obj.hit(&ray, t_min, closest) {
-                Some(point) => {
+                Some(mut point) => {
                     closest = point.t;
+                    point.object = i;
                     current_point = Some(point);
                 }
                 None => {}
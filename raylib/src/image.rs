@@ -0,0 +1,58 @@
+use crate::file::File;
+use crate::png::write_png;
+use crate::vec::Vec3;
+use crate::{clamp, write_color};
+
+// framebuffer of linear rgb colors, top row first
+#[derive(Clone)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: std::vec::Vec<Vec3>,
+}
+
+impl Image {
+    pub fn new(width: usize, height: usize) -> Image {
+        Image {
+            width,
+            height,
+            pixels: vec![Vec3::new(0.0, 0.0, 0.0); width * height],
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Vec3 {
+        self.pixels[y * self.width + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, color: Vec3) {
+        self.pixels[y * self.width + x] = color;
+    }
+
+    // gamma corrected ppm, the same output the chapters write
+    pub fn write(&self, path: &str) {
+        let output = File::new(path, self.height as i64, self.width as i64);
+        for color in self.pixels.iter() {
+            write_color(&output, *color, 1, true);
+        }
+    }
+
+    // ppm without gamma correction, for data such as normals
+    pub fn write_linear(&self, path: &str) {
+        let output = File::new(path, self.height as i64, self.width as i64);
+        for color in self.pixels.iter() {
+            write_color(&output, *color, 1, false);
+        }
+    }
+
+    // gamma corrected png, rounding like write
+    pub fn write_png(&self, path: &str) {
+        let mut rgb = std::vec::Vec::with_capacity(self.pixels.len() * 3);
+        for color in self.pixels.iter() {
+            let color = color.sqrt();
+            for channel in [color.x, color.y, color.z] {
+                rgb.push((256.0 * clamp(channel, 0.0, 0.999)) as u8);
+            }
+        }
+        write_png(path, self.width, self.height, &rgb);
+    }
+}
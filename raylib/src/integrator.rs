@@ -0,0 +1,407 @@
+use crate::background::Background;
+use crate::hittable::{Hit, Hittables};
+use crate::material::Material;
+use crate::random;
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::spectrum::{Color, SampledSpectrum, Wavelengths};
+use crate::vec::Vec3;
+
+// weights one of two sampling strategies for multiple importance sampling (veach, 1997)
+pub fn power_heuristic(pdf: f64, other_pdf: f64) -> f64 {
+    let a = pdf * pdf;
+    let b = other_pdf * other_pdf;
+    if a + b == 0.0 {
+        return 0.0;
+    }
+
+    a / (a + b)
+}
+
+// offset that keeps rays leaving a surface from hitting it again. rays leaving a
+// point inside a medium don't need one, and mustn't skip a boundary close by
+pub(crate) const SURFACE_EPSILON: f64 = 0.001;
+
+// scattering events inside media a path may take on top of its surface bounces,
+// random walks through dense media take many short steps
+const MAX_MEDIUM_EVENTS: i32 = 1024;
+
+pub(crate) fn same_material(a: &dyn Material, b: &dyn Material) -> bool {
+    a as *const dyn Material as *const u8 == b as *const dyn Material as *const u8
+}
+
+// closest hit along the ray, passing through cutout surfaces as often as they are
+// transparent. shadow rays use it too so that cutouts cast matching shadows
+pub(crate) fn intersect<'world>(
+    world: &'world Hittables,
+    ray: &Ray,
+    t_min: f64,
+    t_max: f64,
+    rng: &mut rand::rngs::ThreadRng,
+) -> Option<Hit<'world>> {
+    let mut t_min = t_min;
+    loop {
+        let hit = world.hit(ray, t_min, t_max)?;
+        let alpha = hit.material.map_or(1.0, |m| m.alpha(&hit));
+        if alpha >= 1.0 || random(rng) < alpha {
+            return Some(hit);
+        }
+        t_min = hit.t + SURFACE_EPSILON;
+    }
+}
+
+// closest surface along the ray that isn't a medium boundary, for integrators
+// that leave media out
+pub(crate) fn next_surface<'world>(
+    world: &'world Hittables,
+    ray: &Ray,
+    rng: &mut rand::rngs::ThreadRng,
+) -> Option<Hit<'world>> {
+    let mut t_min = SURFACE_EPSILON;
+    loop {
+        let hit = intersect(world, ray, t_min, f64::INFINITY, rng)?;
+        match hit.material {
+            Some(material) if material.passes_through() => t_min = hit.t + SURFACE_EPSILON,
+            _ => return Some(hit),
+        }
+    }
+}
+
+// whether a surface other than a medium boundary lies along the ray before t_max
+pub(crate) fn occluded(
+    world: &Hittables,
+    ray: &Ray,
+    t_max: f64,
+    rng: &mut rand::rngs::ThreadRng,
+) -> bool {
+    let epsilon = SURFACE_EPSILON / ray.direction.length();
+    let mut t_min = epsilon;
+    loop {
+        let hit = match intersect(world, ray, t_min, t_max, rng) {
+            Some(hit) => hit,
+            None => return false,
+        };
+        match hit.material {
+            Some(material) if material.passes_through() => t_min = hit.t + epsilon,
+            _ => return true,
+        }
+    }
+}
+
+// updates the media a path is inside of as it leaves the hit along `direction`.
+// the geometric normal faces the incoming ray, so a direction on the other side
+// of it has been transmitted through the surface
+fn cross<'world>(
+    media: &mut std::vec::Vec<&'world dyn Material>,
+    material: &'world dyn Material,
+    hit: &Hit,
+    direction: Vec3,
+) {
+    if direction.dot(&hit.geometric_normal) >= 0.0 {
+        return;
+    }
+    if hit.front_face {
+        media.push(material);
+    } else if let Some(i) = media.iter().rposition(|m| same_material(*m, material)) {
+        media.remove(i);
+    }
+}
+
+// fraction of the background's light that reaches the ray's origin along it.
+// surfaces block it apart from medium boundaries, and the media in between
+// attenuate it
+fn shadow_transmittance<'world>(
+    world: &'world Hittables,
+    ray: &Ray,
+    t_min: f64,
+    media: &[&'world dyn Material],
+    rng: &mut rand::rngs::ThreadRng,
+) -> Vec3 {
+    let mut media = media.to_vec();
+    let mut ray = Ray::with_time(ray.origin, ray.direction, ray.time);
+    let mut t_min = t_min;
+    let mut transmittance = Vec3::new(1.0, 1.0, 1.0);
+
+    loop {
+        let hit = intersect(world, &ray, t_min, f64::INFINITY, rng);
+        if let Some(medium) = media.last().and_then(|m| m.interior()) {
+            let distance = hit.map_or(f64::INFINITY, |hit| hit.t * ray.direction.length());
+            let unit = Ray::with_time(ray.origin, ray.direction.unit(), ray.time);
+            transmittance = transmittance * medium.transmittance(&unit, distance, rng);
+        }
+
+        let hit = match hit {
+            Some(hit) => hit,
+            None => return transmittance,
+        };
+        match hit.material {
+            Some(material) if material.passes_through() && !transmittance.near_zero() => {
+                cross(&mut media, material, &hit, ray.direction);
+                ray = Ray::with_time(hit.point, ray.direction, ray.time);
+                t_min = SURFACE_EPSILON;
+            }
+            _ => return Vec3::new(0.0, 0.0, 0.0),
+        }
+    }
+}
+
+// what the rgb and the spectral path tracer differ in: the color the path
+// carries, how the scene's rgb lights and reflectances turn into it, and which
+// of the material's methods evaluate and sample it
+trait PathColor {
+    type Color: Color;
+
+    fn light(&self, rgb: Vec3) -> Self::Color;
+
+    fn reflectance(&self, rgb: Vec3) -> Self::Color;
+
+    fn eval(
+        &self,
+        material: &dyn Material,
+        ray: &Ray,
+        hit: &Hit,
+        direction: Vec3,
+    ) -> Option<(Self::Color, f64)>;
+
+    fn scatter(
+        &mut self,
+        material: &dyn Material,
+        ray: &Ray,
+        hit: Hit,
+        u_lobe: f64,
+        u: (f64, f64),
+        rng: &mut rand::rngs::ThreadRng,
+    ) -> Option<(Ray, Self::Color, Option<f64>)>;
+}
+
+struct Rgb {}
+
+impl PathColor for Rgb {
+    type Color = Vec3;
+
+    fn light(&self, rgb: Vec3) -> Vec3 {
+        rgb
+    }
+
+    fn reflectance(&self, rgb: Vec3) -> Vec3 {
+        rgb
+    }
+
+    fn eval(
+        &self,
+        material: &dyn Material,
+        ray: &Ray,
+        hit: &Hit,
+        direction: Vec3,
+    ) -> Option<(Vec3, f64)> {
+        material.eval(ray, hit, direction)
+    }
+
+    fn scatter(
+        &mut self,
+        material: &dyn Material,
+        ray: &Ray,
+        hit: Hit,
+        u_lobe: f64,
+        u: (f64, f64),
+        rng: &mut rand::rngs::ThreadRng,
+    ) -> Option<(Ray, Vec3, Option<f64>)> {
+        material.scatter_sampled(ray, hit, u_lobe, u, rng)
+    }
+}
+
+// the path's color at the sampled wavelengths
+struct Spectral<'a> {
+    wavelengths: &'a mut Wavelengths,
+}
+
+impl PathColor for Spectral<'_> {
+    type Color = SampledSpectrum;
+
+    fn light(&self, rgb: Vec3) -> SampledSpectrum {
+        SampledSpectrum::from_illuminant(rgb, self.wavelengths)
+    }
+
+    fn reflectance(&self, rgb: Vec3) -> SampledSpectrum {
+        SampledSpectrum::from_rgb(rgb, self.wavelengths)
+    }
+
+    fn eval(
+        &self,
+        material: &dyn Material,
+        ray: &Ray,
+        hit: &Hit,
+        direction: Vec3,
+    ) -> Option<(SampledSpectrum, f64)> {
+        material.eval_spectral(ray, hit, direction, self.wavelengths)
+    }
+
+    fn scatter(
+        &mut self,
+        material: &dyn Material,
+        ray: &Ray,
+        hit: Hit,
+        u_lobe: f64,
+        u: (f64, f64),
+        rng: &mut rand::rngs::ThreadRng,
+    ) -> Option<(Ray, SampledSpectrum, Option<f64>)> {
+        material.scatter_spectral_sampled(ray, hit, self.wavelengths, u_lobe, u, rng)
+    }
+}
+
+// unidirectional path tracer lit by the background and whatever emissive
+// surfaces the path runs into: each bounce samples the background directly and
+// also follows the material's scattered ray, and the two estimates are combined
+// with multiple importance sampling. the materials the path has been
+// transmitted into are tracked so that their interior medium can attenuate it
+// over the distance travelled inside, scatter it there or add the light it emits.
+// every bounce takes the numbers for its background sample and its scatter from
+// the sampler, which has to be started on the path's pixel sample
+pub fn path_trace(
+    ray: &Ray,
+    world: &Hittables,
+    background: &dyn Background,
+    max_depth: i32,
+    sampler: &mut dyn Sampler,
+    rng: &mut rand::rngs::ThreadRng,
+) -> Vec3 {
+    trace(ray, world, background, max_depth, &mut Rgb {}, sampler, rng)
+}
+
+// path_trace for spectral rendering: the path carries radiance at the sampled
+// wavelengths, which dispersive materials may cut down to the hero wavelength
+pub fn path_trace_spectral(
+    ray: &Ray,
+    world: &Hittables,
+    background: &dyn Background,
+    max_depth: i32,
+    wavelengths: &mut Wavelengths,
+    sampler: &mut dyn Sampler,
+    rng: &mut rand::rngs::ThreadRng,
+) -> SampledSpectrum {
+    trace(
+        ray,
+        world,
+        background,
+        max_depth,
+        &mut Spectral { wavelengths },
+        sampler,
+        rng,
+    )
+}
+
+fn trace<P: PathColor>(
+    ray: &Ray,
+    world: &Hittables,
+    background: &dyn Background,
+    max_depth: i32,
+    colors: &mut P,
+    sampler: &mut dyn Sampler,
+    rng: &mut rand::rngs::ThreadRng,
+) -> P::Color {
+    let mut color = P::Color::splat(0.0);
+    let mut throughput = P::Color::splat(1.0);
+    let mut ray = Ray::with_time(ray.origin, ray.direction, ray.time);
+
+    // pdf of the previous scatter, None for camera rays and specular bounces
+    let mut scatter_pdf: Option<f64> = None;
+
+    // materials the path is currently inside of, innermost last
+    let mut media: std::vec::Vec<&dyn Material> = std::vec::Vec::new();
+
+    let mut depth = 0;
+    let mut medium_events = 0;
+    let mut t_min = SURFACE_EPSILON;
+    while depth < max_depth && medium_events < MAX_MEDIUM_EVENTS {
+        let hit = intersect(world, &ray, t_min, f64::INFINITY, rng);
+
+        if let Some(medium) = media.last().and_then(|m| m.interior()) {
+            let distance = hit.map_or(f64::INFINITY, |hit| hit.t * ray.direction.length());
+            let unit = Ray::with_time(ray.origin, ray.direction.unit(), ray.time);
+            let sample = medium.sample(&unit, distance, rng);
+            color = color + throughput * colors.light(sample.emission);
+            throughput = throughput * colors.reflectance(sample.weight);
+
+            if let Some(t) = sample.scattered_at {
+                let point = unit.at(t);
+
+                let u_light = sampler.get_2d(rng);
+                if let Some((light_direction, radiance, light_pdf)) = background.sample(u_light) {
+                    let phase = medium.phase(unit.direction, light_direction);
+                    if phase > 0.0 {
+                        let shadow = Ray::with_time(point, light_direction, ray.time);
+                        let transmittance = shadow_transmittance(world, &shadow, 0.0, &media, rng);
+                        let weight = power_heuristic(light_pdf, phase) / light_pdf;
+                        let light = colors.light(radiance) * colors.reflectance(transmittance);
+                        color = color + throughput * light * (phase * weight);
+                    }
+                }
+
+                // the phase function is its own pdf, so the throughput stays as is
+                let (scattered, pdf) = medium.sample_phase(unit.direction, rng);
+                scatter_pdf = Some(pdf);
+                ray = Ray::with_time(point, scattered, ray.time);
+                t_min = 0.0;
+                medium_events += 1;
+                continue;
+            }
+        }
+
+        let hit = match hit {
+            Some(hit) => hit,
+            None => {
+                let weight = match scatter_pdf {
+                    Some(pdf) => power_heuristic(pdf, background.pdf(ray.direction)),
+                    None => 1.0,
+                };
+                let light = colors.light(background.color(ray.direction));
+                return color + throughput * light * weight;
+            }
+        };
+        let material = match hit.material {
+            Some(material) => material,
+            None => panic!("expecting material"),
+        };
+
+        color = color + throughput * colors.light(material.emitted(&hit));
+
+        if material.passes_through() {
+            cross(&mut media, material, &hit, ray.direction);
+            ray = Ray::with_time(hit.point, ray.direction, ray.time);
+            t_min = SURFACE_EPSILON;
+            medium_events += 1;
+            continue;
+        }
+        depth += 1;
+
+        let u_light = sampler.get_2d(rng);
+        if let Some((direction, radiance, light_pdf)) = background.sample(u_light) {
+            if let Some((f, pdf)) = colors.eval(material, &ray, &hit, direction) {
+                if !f.is_black() {
+                    let mut shadow_media = media.clone();
+                    cross(&mut shadow_media, material, &hit, direction);
+                    let shadow = Ray::with_time(hit.point, direction, ray.time);
+                    let transmittance =
+                        shadow_transmittance(world, &shadow, SURFACE_EPSILON, &shadow_media, rng);
+                    let weight = power_heuristic(light_pdf, pdf) / light_pdf;
+                    let light = colors.light(radiance) * colors.reflectance(transmittance);
+                    color = color + throughput * f * light * weight;
+                }
+            }
+        }
+
+        let u_lobe = sampler.get_1d(rng);
+        let u = sampler.get_2d(rng);
+        match colors.scatter(material, &ray, hit, u_lobe, u, rng) {
+            Some((scattered, attenuation, pdf)) => {
+                scatter_pdf = pdf;
+                throughput = throughput * attenuation;
+                cross(&mut media, material, &hit, scattered.direction);
+                ray = Ray::with_time(scattered.origin, scattered.direction, ray.time);
+                t_min = SURFACE_EPSILON;
+            }
+            None => return color,
+        }
+    }
+    color
+}
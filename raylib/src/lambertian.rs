@@ -1,8 +1,11 @@
 use crate::hittable::Hit;
-use crate::material::Material;
-use crate::random_unit_vector;
+use crate::material::{upsampled, Material};
 use crate::ray::Ray;
+use crate::spectrum::{SampledSpectrum, Wavelengths};
 use crate::vec::Vec3;
+use crate::{random_unit_vector, uniform_sphere};
+
+use std::f64::consts::PI;
 
 pub struct Lambertian {
     pub albedo: Vec3,
@@ -30,4 +33,42 @@ impl Material for Lambertian {
 
         return Some((scattered, self.albedo));
     }
+
+    // the same normal plus unit vector as scatter, with the unit vector from u
+    fn scatter_sampled(
+        &self,
+        ray: &Ray,
+        hit: Hit,
+        _u_lobe: f64,
+        u: (f64, f64),
+        _rng: &mut rand::rngs::ThreadRng,
+    ) -> Option<(Ray, Vec3, Option<f64>)> {
+        let mut scatter_direction = hit.normal + uniform_sphere(u.0, u.1);
+
+        if scatter_direction.near_zero() {
+            scatter_direction = hit.normal;
+        }
+        let pdf = self.eval(ray, &hit, scatter_direction).map(|(_, pdf)| pdf);
+
+        Some((Ray::new(hit.point, scatter_direction), self.albedo, pdf))
+    }
+
+    fn scatter_spectral_sampled(
+        &self,
+        ray: &Ray,
+        hit: Hit,
+        wavelengths: &mut Wavelengths,
+        u_lobe: f64,
+        u: (f64, f64),
+        rng: &mut rand::rngs::ThreadRng,
+    ) -> Option<(Ray, SampledSpectrum, Option<f64>)> {
+        upsampled(self.scatter_sampled(ray, hit, u_lobe, u, rng), wavelengths)
+    }
+
+    fn eval(&self, _ray: &Ray, hit: &Hit, direction: Vec3) -> Option<(Vec3, f64)> {
+        // normal + random_unit_vector is distributed by cosine over the hemisphere
+        let cosine = direction.unit().dot(&hit.normal).max(0.0);
+
+        Some((self.albedo * (cosine / PI), cosine / PI))
+    }
 }
@@ -0,0 +1,267 @@
+use crate::microfacet::refract;
+use crate::vec::Vec3;
+use crate::{concentric_disk, random};
+
+use std::fs;
+use std::path::Path;
+
+// one spherical surface of a lens, or the aperture stop when the radius is 0.
+// all lengths are in meters
+#[derive(Copy, Clone, Debug)]
+pub struct LensElement {
+    // radius of curvature, positive when the surface bulges towards the scene
+    pub radius: f64,
+    // distance along the axis to the next surface, or to the film for the last
+    pub thickness: f64,
+    // index of refraction behind the surface, towards the film. 0 and 1 are air
+    pub index_of_refraction: f64,
+    // radius of the opening, past which light is blocked
+    pub aperture: f64,
+}
+
+impl LensElement {
+    fn index(&self) -> f64 {
+        if self.index_of_refraction == 0.0 {
+            1.0
+        } else {
+            self.index_of_refraction
+        }
+    }
+}
+
+// a real lens as a stack of spherical elements (kolb et al., 1995), listed from
+// the scene side to the film side. rays are traced through every surface in lens
+// space, where the film is the plane z = 0 and the scene lies towards -z
+#[derive(Clone, Debug)]
+pub struct LensSystem {
+    pub elements: std::vec::Vec<LensElement>,
+}
+
+impl LensSystem {
+    pub fn new(elements: std::vec::Vec<LensElement>) -> LensSystem {
+        if elements.is_empty() {
+            panic!("error lens system has no elements");
+        }
+        LensSystem { elements }
+    }
+
+    // a prescription as lens design tables and pbrt list them: one surface per
+    // line with radius, thickness, index of refraction and aperture diameter, all
+    // in millimeters. lines starting with # are comments
+    pub fn open(path: &str) -> LensSystem {
+        let text = match fs::read_to_string(Path::new(path)) {
+            Err(err) => panic!("error {}", err),
+            Ok(text) => text,
+        };
+
+        let mut elements = std::vec::Vec::new();
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let values: std::vec::Vec<f64> = line
+                .split_whitespace()
+                .map(|field| match field.parse::<f64>() {
+                    Ok(value) => value,
+                    Err(err) => panic!("error {} in lens prescription", err),
+                })
+                .collect();
+            if values.len() != 4 {
+                panic!("error bad lens prescription line {}", line);
+            }
+            elements.push(LensElement {
+                radius: values[0] / 1000.0,
+                thickness: values[1] / 1000.0,
+                index_of_refraction: values[2],
+                aperture: values[3] / 2000.0,
+            });
+        }
+
+        LensSystem::new(elements)
+    }
+
+    fn rear(&self) -> &LensElement {
+        &self.elements[self.elements.len() - 1]
+    }
+
+    fn rear_z(&self) -> f64 {
+        -self.rear().thickness
+    }
+
+    fn front_z(&self) -> f64 {
+        -self.elements.iter().map(|e| e.thickness).sum::<f64>()
+    }
+
+    // traces a ray through every surface, from the film out into the scene or
+    // the other way round. None when an element's rim, the stop or total
+    // internal reflection blocks it
+    fn trace(&self, origin: Vec3, direction: Vec3, from_film: bool) -> Option<(Vec3, Vec3)> {
+        let n = self.elements.len();
+        let mut origin = origin;
+        let mut direction = direction.unit();
+        let mut z = if from_film { 0.0 } else { self.front_z() };
+
+        for k in 0..n {
+            let i = if from_film { n - 1 - k } else { k };
+            let element = &self.elements[i];
+            if from_film {
+                z -= element.thickness;
+            } else if k > 0 {
+                z += self.elements[i - 1].thickness;
+            }
+
+            let (t, normal) = if element.radius == 0.0 {
+                if direction.z == 0.0 {
+                    return None;
+                }
+                ((z - origin.z) / direction.z, Vec3::new(0.0, 0.0, 1.0))
+            } else {
+                let center = Vec3::new(0.0, 0.0, z + element.radius);
+                let oc = origin - center;
+                let b = oc.dot(&direction);
+                let c = oc.length_squared() - element.radius * element.radius;
+                let discriminant = b * b - c;
+                if discriminant < 0.0 {
+                    return None;
+                }
+                // the cap around the vertex rather than the far side of the sphere
+                let root = discriminant.sqrt();
+                let closer = (direction.z > 0.0) != (element.radius < 0.0);
+                let t = if closer { -b - root } else { -b + root };
+                (t, (origin + direction * t - center) / element.radius.abs())
+            };
+            if t <= 0.0 {
+                return None;
+            }
+
+            let point = origin + direction * t;
+            if point.x * point.x + point.y * point.y > element.aperture * element.aperture {
+                return None;
+            }
+            origin = point;
+
+            if element.radius != 0.0 {
+                let outside = if i > 0 {
+                    self.elements[i - 1].index()
+                } else {
+                    1.0
+                };
+                let (eta_i, eta_t) = if from_film {
+                    (element.index(), outside)
+                } else {
+                    (outside, element.index())
+                };
+                let normal = if normal.dot(&direction) > 0.0 {
+                    -normal
+                } else {
+                    normal
+                };
+                direction = refract(-direction, normal, eta_t / eta_i)?;
+            }
+        }
+
+        Some((origin, direction))
+    }
+
+    // a ray from the point on the film out of the front of the lens, through the
+    // point u of the unit square mapped uniformly onto the rear element. rays are
+    // kept with probability cos^4 of their angle to the axis, which gives the
+    // natural falloff towards the image corners without weighting samples
+    pub fn sample(
+        &self,
+        film_x: f64,
+        film_y: f64,
+        u: (f64, f64),
+        rng: &mut rand::rngs::ThreadRng,
+    ) -> Option<(Vec3, Vec3)> {
+        let rd = concentric_disk(u.0, u.1) * self.rear().aperture;
+        let film = Vec3::new(film_x, film_y, 0.0);
+        let direction = Vec3::new(rd.x, rd.y, self.rear_z()) - film;
+
+        let cos_theta = direction.z.abs() / direction.length();
+        if random(rng) >= cos_theta.powi(4) {
+            return None;
+        }
+        self.trace(film, direction, true)
+    }
+
+    // share of sample rays from the point on the film that make it out, counting
+    // the cos^4 falloff, over a fixed grid on the rear element
+    pub fn transmission(&self, film_x: f64, film_y: f64) -> f64 {
+        let n = 64;
+        let rear = self.rear().aperture;
+        let film = Vec3::new(film_x, film_y, 0.0);
+
+        let mut total = 0.0;
+        let mut count = 0;
+        for i in 0..n {
+            for j in 0..n {
+                let x = ((i as f64 + 0.5) / n as f64 * 2.0 - 1.0) * rear;
+                let y = ((j as f64 + 0.5) / n as f64 * 2.0 - 1.0) * rear;
+                if x * x + y * y > rear * rear {
+                    continue;
+                }
+                count += 1;
+                let direction = Vec3::new(x, y, self.rear_z()) - film;
+                if self.trace(film, direction, true).is_some() {
+                    total += (direction.z.abs() / direction.length()).powi(4);
+                }
+            }
+        }
+        if count == 0 {
+            0.0
+        } else {
+            total / count as f64
+        }
+    }
+
+    // where a ray parallel to the axis at height h crosses the axis after the
+    // lens, and where it would have bent to in a single thin lens: the focal
+    // point and principal plane on that side
+    fn cardinal_points(&self, h: f64, from_film: bool) -> (f64, f64) {
+        let (origin, direction) = if from_film {
+            (Vec3::new(h, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0))
+        } else {
+            (
+                Vec3::new(h, 0.0, self.front_z() - 1.0),
+                Vec3::new(0.0, 0.0, 1.0),
+            )
+        };
+        let (o, d) = match self.trace(origin, direction, from_film) {
+            Some(out) if out.1.x != 0.0 => out,
+            _ => panic!("error lens system doesn't focus paraxial rays"),
+        };
+        let focal = o.z + d.z * (-o.x / d.x);
+        let principal = o.z + d.z * ((h - o.x) / d.x);
+        (focal, principal)
+    }
+
+    // moves the lens along the axis so objects at distance from the film are in
+    // focus, using the thick lens the elements approximate
+    pub fn focus(&mut self, distance: f64) {
+        let h = 0.01
+            * self
+                .elements
+                .iter()
+                .map(|e| e.aperture)
+                .fold(f64::INFINITY, f64::min);
+        let (_, front) = self.cardinal_points(h, true);
+        let (focal, rear) = self.cardinal_points(h, false);
+        let f = focal - rear;
+
+        // shifting by delta towards the scene, the object sits front - delta +
+        // distance in front of one principal plane and the film delta - rear
+        // behind the other, which must satisfy the thin lens equation
+        let a = front + distance;
+        let b = -rear;
+        let c = (a + b) * (a + b - 4.0 * f);
+        if f <= 0.0 || c < 0.0 {
+            panic!("error lens system can't focus at {}", distance);
+        }
+        let delta = 0.5 * ((a - b) - c.sqrt());
+
+        let last = self.elements.len() - 1;
+        self.elements[last].thickness += delta;
+    }
+}
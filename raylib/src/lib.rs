@@ -1,15 +1,59 @@
 use rand::prelude::*;
 
+pub mod adaptive;
+pub mod animated;
+pub mod animation;
+pub mod aov;
+pub mod aperture;
+pub mod background;
+pub mod bdpt;
 pub mod camera;
+pub mod camera_builder;
+pub mod coated;
+pub mod conductor;
+pub mod cuboid;
+pub mod cutout;
 pub mod dielectric;
+pub mod diffuse_light;
+pub mod dispersive;
+pub mod distribution;
+pub mod exposure;
 pub mod file;
+pub mod hdr;
+pub mod heterogeneous;
 pub mod hittable;
+pub mod image;
+pub mod integrator;
 pub mod lambertian;
+pub mod lens_system;
+pub mod lights;
 pub mod material;
+pub mod medium;
 pub mod metal;
+pub mod microfacet;
+pub mod mix;
+pub mod onb;
+pub mod perturbed;
+pub mod photon_map;
+pub mod png;
+pub mod principled;
+pub mod quaternion;
 pub mod ray;
+pub mod render;
+pub mod rough_dielectric;
+pub mod sampler;
+pub mod sky;
+pub mod spectrum;
 pub mod sphere;
+pub mod sppm;
+pub mod stereo;
+pub mod subsurface;
+pub mod texture;
+pub mod thin_film;
+pub mod two_sided;
 pub mod vec;
+pub mod volume;
+pub mod voxel;
 
 pub fn random(rng: &mut rand::rngs::ThreadRng) -> f64 {
     rng.gen()
@@ -47,6 +91,32 @@ pub fn random_in_unit_disk(rng: &mut rand::rngs::ThreadRng) -> vec::Vec3 {
     }
 }
 
+// maps a point of the unit square onto the unit disk keeping areas and
+// neighbours (shirley and chiu, 1997), so stratified samples stay stratified
+pub fn concentric_disk(u: f64, v: f64) -> vec::Vec3 {
+    let a = 2.0 * u - 1.0;
+    let b = 2.0 * v - 1.0;
+    if a == 0.0 && b == 0.0 {
+        return vec::Vec3::new(0.0, 0.0, 0.0);
+    }
+    let quarter = std::f64::consts::FRAC_PI_4;
+    let (r, theta) = if a.abs() > b.abs() {
+        (a, quarter * (b / a))
+    } else {
+        (b, 2.0 * quarter - quarter * (a / b))
+    };
+    vec::Vec3::new(r * theta.cos(), r * theta.sin(), 0.0)
+}
+
+// maps a point of the unit square onto the unit sphere keeping areas, for
+// uniform directions drawn from a sampler
+pub fn uniform_sphere(u: f64, v: f64) -> vec::Vec3 {
+    let z = 1.0 - 2.0 * u;
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * std::f64::consts::PI * v;
+    vec::Vec3::new(r * phi.cos(), r * phi.sin(), z)
+}
+
 pub fn random_in_hemisphere(normal: &vec::Vec3, rng: &mut rand::rngs::ThreadRng) -> vec::Vec3 {
     let in_unit_sphere = random_in_unit_sphere(rng);
     if in_unit_sphere.dot(normal) > 0.0 {
@@ -69,6 +139,15 @@ pub fn random_in_unit_sphere(rng: &mut rand::rngs::ThreadRng) -> vec::Vec3 {
     }
 }
 
+// cie xyz to linear srgb (d65 white point)
+pub fn xyz_to_rgb(xyz: vec::Vec3) -> vec::Vec3 {
+    vec::Vec3::new(
+        3.2404542 * xyz.x - 1.5371385 * xyz.y - 0.4985314 * xyz.z,
+        -0.9692660 * xyz.x + 1.8760108 * xyz.y + 0.0415560 * xyz.z,
+        0.0556434 * xyz.x - 0.2040259 * xyz.y + 1.0572252 * xyz.z,
+    )
+}
+
 pub fn write_color_03_3(output: &file::File, color: vec::Vec3) {
     output.write(vec::Vec3::new(
         255.999 * color.x,
@@ -0,0 +1,77 @@
+use crate::hittable::{Hittable, Hittables};
+use crate::integrator::same_material;
+use crate::material::Material;
+use crate::random;
+
+// emissive shape of the world
+pub struct Light<'world> {
+    pub object: &'world dyn Hittable,
+    pub material: &'world dyn Material,
+    pub area: f64,
+}
+
+// the emissive shapes of the world, for integrators that start paths from the
+// lights. the background counts as one more light, picked as often as any shape,
+// while a shape is picked by its area so points are uniform over all of them
+pub struct Lights<'world> {
+    pub list: std::vec::Vec<Light<'world>>,
+    pub area: f64,
+}
+
+impl<'world> Lights<'world> {
+    pub fn new(world: &'world Hittables, rng: &mut rand::rngs::ThreadRng) -> Lights<'world> {
+        let mut list = std::vec::Vec::new();
+        for object in world.list.iter() {
+            let hit = match object.sample(rng) {
+                Some(hit) => hit,
+                None => continue,
+            };
+            if let Some(material) = hit.material {
+                if !material.emitted(&hit).near_zero() && object.area() > 0.0 {
+                    list.push(Light {
+                        object: object.as_ref(),
+                        material,
+                        area: object.area(),
+                    });
+                }
+            }
+        }
+        let area = list.iter().map(|light| light.area).sum();
+
+        Lights { list, area }
+    }
+
+    pub fn background_probability(&self) -> f64 {
+        1.0 / (self.list.len() as f64 + 1.0)
+    }
+
+    // None picks the background
+    pub fn pick(&self, rng: &mut rand::rngs::ThreadRng) -> Option<&Light<'world>> {
+        if random(rng) < self.background_probability() {
+            return None;
+        }
+        let mut pick = random(rng) * self.area;
+        for light in self.list.iter() {
+            if pick < light.area {
+                return Some(light);
+            }
+            pick -= light.area;
+        }
+        self.list.last()
+    }
+
+    // pdf per unit area of picking a point on the shapes, a light and then a point
+    // uniformly on it
+    pub fn pdf_position(&self) -> f64 {
+        if self.area <= 0.0 {
+            return 0.0;
+        }
+        (1.0 - self.background_probability()) / self.area
+    }
+
+    pub fn contains(&self, material: &dyn Material) -> bool {
+        self.list
+            .iter()
+            .any(|light| same_material(light.material, material))
+    }
+}
@@ -1,7 +1,123 @@
 use crate::hittable::Hit;
+use crate::medium::Medium;
 use crate::ray::Ray;
+use crate::spectrum::{SampledSpectrum, Wavelengths};
 use crate::vec::Vec3;
 
+// rgb scatter_with_pdf results upsampled to a spectrum at the path's wavelengths
+pub(crate) fn upsampled(
+    scattered: Option<(Ray, Vec3, Option<f64>)>,
+    wavelengths: &Wavelengths,
+) -> Option<(Ray, SampledSpectrum, Option<f64>)> {
+    scattered.map(|(scattered, attenuation, pdf)| {
+        (
+            scattered,
+            SampledSpectrum::from_rgb(attenuation, wavelengths),
+            pdf,
+        )
+    })
+}
+
 pub trait Material {
     fn scatter(&self, ray: &Ray, hit: Hit, rng: &mut rand::rngs::ThreadRng) -> Option<(Ray, Vec3)>;
+
+    // bsdf times cosine for light leaving towards `direction`, along with the pdf
+    // `scatter` would have chosen that direction with; None for materials that
+    // only scatter into discrete (specular) directions
+    fn eval(&self, _ray: &Ray, _hit: &Hit, _direction: Vec3) -> Option<(Vec3, f64)> {
+        None
+    }
+
+    // scatter along with the pdf of the scattered direction, which integrators need
+    // to weight the sample against light sampling. None when the direction came
+    // from a specular lobe. materials mixing specular and non-specular lobes
+    // override this, for the rest eval already knows the pdf
+    fn scatter_with_pdf(
+        &self,
+        ray: &Ray,
+        hit: Hit,
+        rng: &mut rand::rngs::ThreadRng,
+    ) -> Option<(Ray, Vec3, Option<f64>)> {
+        let (scattered, attenuation) = self.scatter(ray, hit, rng)?;
+        let pdf = self
+            .eval(ray, &hit, scattered.direction)
+            .map(|(_, pdf)| pdf);
+
+        Some((scattered, attenuation, pdf))
+    }
+
+    // scatter_with_pdf with the sampler's numbers behind its choices, u_lobe
+    // picking the lobe and u the direction within it, so well spread samples
+    // keep helping past the camera ray. materials that don't take them draw
+    // from rng as before
+    fn scatter_sampled(
+        &self,
+        ray: &Ray,
+        hit: Hit,
+        _u_lobe: f64,
+        _u: (f64, f64),
+        rng: &mut rand::rngs::ThreadRng,
+    ) -> Option<(Ray, Vec3, Option<f64>)> {
+        self.scatter_with_pdf(ray, hit, rng)
+    }
+
+    // scatter_with_pdf for spectral rendering, the attenuation is sampled at the
+    // path's wavelengths. materials whose scattering depends on the wavelength
+    // override this, the rest have their rgb attenuation upsampled to a spectrum
+    fn scatter_spectral(
+        &self,
+        ray: &Ray,
+        hit: Hit,
+        wavelengths: &mut Wavelengths,
+        rng: &mut rand::rngs::ThreadRng,
+    ) -> Option<(Ray, SampledSpectrum, Option<f64>)> {
+        upsampled(self.scatter_with_pdf(ray, hit, rng), wavelengths)
+    }
+
+    // scatter_spectral with the sampler's numbers, as in scatter_sampled
+    fn scatter_spectral_sampled(
+        &self,
+        ray: &Ray,
+        hit: Hit,
+        wavelengths: &mut Wavelengths,
+        _u_lobe: f64,
+        _u: (f64, f64),
+        rng: &mut rand::rngs::ThreadRng,
+    ) -> Option<(Ray, SampledSpectrum, Option<f64>)> {
+        self.scatter_spectral(ray, hit, wavelengths, rng)
+    }
+
+    fn eval_spectral(
+        &self,
+        ray: &Ray,
+        hit: &Hit,
+        direction: Vec3,
+        wavelengths: &Wavelengths,
+    ) -> Option<(SampledSpectrum, f64)> {
+        self.eval(ray, hit, direction)
+            .map(|(f, pdf)| (SampledSpectrum::from_rgb(f, wavelengths), pdf))
+    }
+
+    // light the surface emits back along the incoming ray
+    fn emitted(&self, _hit: &Hit) -> Vec3 {
+        Vec3::new(0.0, 0.0, 0.0)
+    }
+
+    // medium filling the inside of surfaces made of this material, light that is
+    // transmitted into them passes through it until it leaves again
+    fn interior(&self) -> Option<&dyn Medium> {
+        None
+    }
+
+    // opacity of the surface at the hit, rays pass straight through the rest of
+    // the time as if nothing had been hit
+    fn alpha(&self, _hit: &Hit) -> f64 {
+        1.0
+    }
+
+    // true for surfaces that only mark where a medium begins and ends. paths and
+    // shadow rays cross them without scattering
+    fn passes_through(&self) -> bool {
+        false
+    }
 }
@@ -0,0 +1,229 @@
+use crate::onb::Onb;
+use crate::random;
+use crate::ray::Ray;
+use crate::vec::Vec3;
+
+use std::f64::consts::PI;
+
+// what happened to a path travelling through a medium, see Medium::sample
+pub struct MediumSample {
+    // distance along the ray at which the path scatters, None when it got through
+    pub scattered_at: Option<f64>,
+    // multiplies the path throughput either way
+    pub weight: Vec3,
+    // light the medium emitted towards the path, before the weight applies
+    pub emission: Vec3,
+}
+
+impl MediumSample {
+    pub fn passed(weight: Vec3) -> MediumSample {
+        MediumSample {
+            scattered_at: None,
+            weight,
+            emission: Vec3::new(0.0, 0.0, 0.0),
+        }
+    }
+}
+
+// whatever fills the inside of a closed surface, see Material::interior. the rays
+// passed in have unit directions and start where the path entered the segment
+pub trait Medium {
+    // fraction of light that survives travelling `distance` along the ray. media
+    // that vary in space estimate it, so it can be noisy
+    fn transmittance(&self, ray: &Ray, distance: f64, rng: &mut rand::rngs::ThreadRng) -> Vec3;
+
+    // samples how far the path travels along the ray before it scatters inside
+    // the medium, up to `distance`. media that only absorb never scatter
+    fn sample(&self, ray: &Ray, distance: f64, rng: &mut rand::rngs::ThreadRng) -> MediumSample {
+        MediumSample::passed(self.transmittance(ray, distance, rng))
+    }
+
+    // phase function for light travelling along `direction` that scatters into
+    // `scattered`, both unit vectors
+    fn phase(&self, direction: Vec3, scattered: Vec3) -> f64 {
+        HenyeyGreenstein::new(0.0).eval(direction, scattered)
+    }
+
+    // scattered direction and its pdf, which is the phase function itself
+    fn sample_phase(&self, direction: Vec3, rng: &mut rand::rngs::ThreadRng) -> (Vec3, f64) {
+        HenyeyGreenstein::new(0.0).sample(direction, rng)
+    }
+}
+
+// exp(-sigma * distance) per channel, without the nan a zero coefficient would
+// give over an infinite distance
+fn beer_lambert(sigma: Vec3, distance: f64) -> Vec3 {
+    let channel = |sigma: f64| {
+        if sigma > 0.0 {
+            (-sigma * distance).exp()
+        } else {
+            1.0
+        }
+    };
+
+    Vec3::new(channel(sigma.x), channel(sigma.y), channel(sigma.z))
+}
+
+fn channel(v: Vec3, i: usize) -> f64 {
+    match i {
+        0 => v.x,
+        1 => v.y,
+        _ => v.z,
+    }
+}
+
+// phase function with a single parameter g in (-1, 1): positive values scatter
+// forward like most natural media, 0 is isotropic
+#[derive(Copy, Clone, Debug)]
+pub struct HenyeyGreenstein {
+    pub g: f64,
+}
+
+impl HenyeyGreenstein {
+    pub fn new(g: f64) -> HenyeyGreenstein {
+        HenyeyGreenstein {
+            g: g.clamp(-0.99, 0.99),
+        }
+    }
+
+    pub fn eval(&self, direction: Vec3, scattered: Vec3) -> f64 {
+        let cos_theta = direction.dot(&scattered);
+        let denominator = 1.0 + self.g * self.g - 2.0 * self.g * cos_theta;
+
+        (1.0 - self.g * self.g) / (4.0 * PI * denominator * denominator.max(1e-12).sqrt())
+    }
+
+    pub fn sample(&self, direction: Vec3, rng: &mut rand::rngs::ThreadRng) -> (Vec3, f64) {
+        let g = self.g;
+        let u = random(rng);
+        let cos_theta = if g.abs() < 1e-3 {
+            1.0 - 2.0 * u
+        } else {
+            let s = (1.0 - g * g) / (1.0 - g + 2.0 * g * u);
+            ((1.0 + g * g - s * s) / (2.0 * g)).clamp(-1.0, 1.0)
+        };
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * random(rng);
+
+        let scattered =
+            Onb::from_w(direction).local(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta);
+        (scattered, self.eval(direction, scattered))
+    }
+}
+
+// beer-lambert absorption, for colored glass and liquids
+#[derive(Copy, Clone, Debug)]
+pub struct Absorbing {
+    // absorption coefficient per unit of distance
+    pub sigma_a: Vec3,
+}
+
+impl Absorbing {
+    pub fn new(sigma_a: Vec3) -> Absorbing {
+        Absorbing { sigma_a }
+    }
+
+    // absorption that leaves `color` after light travels `distance`, easier to pick
+    // than a coefficient
+    pub fn from_color(color: Vec3, distance: f64) -> Absorbing {
+        let coefficient = |c: f64| -c.max(1e-6).ln() / distance;
+
+        Absorbing::new(Vec3::new(
+            coefficient(color.x),
+            coefficient(color.y),
+            coefficient(color.z),
+        ))
+    }
+}
+
+impl Medium for Absorbing {
+    fn transmittance(&self, _ray: &Ray, distance: f64, _rng: &mut rand::rngs::ThreadRng) -> Vec3 {
+        beer_lambert(self.sigma_a, distance)
+    }
+}
+
+// homogeneous medium that scatters as well as absorbs, like milk, skin or smoke
+#[derive(Copy, Clone, Debug)]
+pub struct Scattering {
+    pub sigma_a: Vec3,
+    pub sigma_s: Vec3,
+    pub phase: HenyeyGreenstein,
+}
+
+impl Scattering {
+    pub fn new(sigma_a: Vec3, sigma_s: Vec3, g: f64) -> Scattering {
+        Scattering {
+            sigma_a,
+            sigma_s,
+            phase: HenyeyGreenstein::new(g),
+        }
+    }
+
+    // coefficients from the color the medium ends up with after many bounces and
+    // the mean distance between them, using the fit of chiang et al. 2016 to map
+    // that color to the single scattering albedo
+    pub fn from_albedo(albedo: Vec3, mean_free_path: Vec3, g: f64) -> Scattering {
+        let single = |a: f64| {
+            let a = a.clamp(0.0, 1.0);
+            let s = 4.09712 + 4.20863 * a - (9.59217 + 41.6808 * a + 17.7126 * a * a).sqrt();
+            1.0 - s * s
+        };
+        let albedo = Vec3::new(single(albedo.x), single(albedo.y), single(albedo.z));
+        let sigma_t = Vec3::new(
+            1.0 / mean_free_path.x.max(1e-6),
+            1.0 / mean_free_path.y.max(1e-6),
+            1.0 / mean_free_path.z.max(1e-6),
+        );
+        let one = Vec3::new(1.0, 1.0, 1.0);
+
+        Scattering::new(sigma_t * (one - albedo), sigma_t * albedo, g)
+    }
+
+    fn sigma_t(&self) -> Vec3 {
+        self.sigma_a + self.sigma_s
+    }
+}
+
+impl Medium for Scattering {
+    fn transmittance(&self, _ray: &Ray, distance: f64, _rng: &mut rand::rngs::ThreadRng) -> Vec3 {
+        beer_lambert(self.sigma_t(), distance)
+    }
+
+    // distances are sampled with the extinction of a randomly picked channel and
+    // weighted by the average pdf over all three
+    fn sample(&self, _ray: &Ray, distance: f64, rng: &mut rand::rngs::ThreadRng) -> MediumSample {
+        let sigma_t = self.sigma_t();
+        let picked = channel(sigma_t, ((random(rng) * 3.0) as usize).min(2));
+        let t = if picked > 0.0 {
+            -(1.0 - random(rng)).ln() / picked
+        } else {
+            f64::INFINITY
+        };
+
+        if t < distance {
+            let transmittance = beer_lambert(sigma_t, t);
+            let density = sigma_t * transmittance;
+            let pdf = (density.x + density.y + density.z) / 3.0;
+            return MediumSample {
+                scattered_at: Some(t),
+                weight: transmittance * self.sigma_s / pdf,
+                emission: Vec3::new(0.0, 0.0, 0.0),
+            };
+        }
+
+        let transmittance = beer_lambert(sigma_t, distance);
+        let pdf = (transmittance.x + transmittance.y + transmittance.z) / 3.0;
+        if pdf <= 0.0 {
+            return MediumSample::passed(Vec3::new(0.0, 0.0, 0.0));
+        }
+        MediumSample::passed(transmittance / pdf)
+    }
+
+    fn phase(&self, direction: Vec3, scattered: Vec3) -> f64 {
+        self.phase.eval(direction, scattered)
+    }
+
+    fn sample_phase(&self, direction: Vec3, rng: &mut rand::rngs::ThreadRng) -> (Vec3, f64) {
+        self.phase.sample(direction, rng)
+    }
+}
@@ -0,0 +1,167 @@
+use crate::vec::Vec3;
+
+use std::f64::consts::PI;
+
+// below this alpha a surface is treated as a perfectly smooth interface
+pub const SMOOTH_ALPHA: f64 = 1e-3;
+
+// ggx / trowbridge-reitz distribution of microfacet normals. everything works in
+// the local shading frame where the macro normal is +z
+#[derive(Copy, Clone, Debug)]
+pub struct Ggx {
+    pub alpha_x: f64,
+    pub alpha_y: f64,
+}
+
+impl Ggx {
+    // perceptual roughness in [0, 1], squared to get alpha
+    pub fn new(roughness: f64) -> Ggx {
+        Ggx::anisotropic(roughness, roughness)
+    }
+
+    pub fn anisotropic(roughness_x: f64, roughness_y: f64) -> Ggx {
+        let rx = roughness_x.clamp(0.0, 1.0);
+        let ry = roughness_y.clamp(0.0, 1.0);
+
+        Ggx {
+            alpha_x: rx * rx,
+            alpha_y: ry * ry,
+        }
+    }
+
+    pub fn is_smooth(&self) -> bool {
+        self.alpha_x.max(self.alpha_y) < SMOOTH_ALPHA
+    }
+
+    pub fn d(&self, m: Vec3) -> f64 {
+        if m.z <= 0.0 {
+            return 0.0;
+        }
+        let x = m.x / self.alpha_x;
+        let y = m.y / self.alpha_y;
+        let denominator = x * x + y * y + m.z * m.z;
+
+        1.0 / (PI * self.alpha_x * self.alpha_y * denominator * denominator)
+    }
+
+    pub fn lambda(&self, w: Vec3) -> f64 {
+        if w.z == 0.0 {
+            return f64::INFINITY;
+        }
+        let a2 = (self.alpha_x * self.alpha_x * w.x * w.x
+            + self.alpha_y * self.alpha_y * w.y * w.y)
+            / (w.z * w.z);
+
+        0.5 * (-1.0 + (1.0 + a2).sqrt())
+    }
+
+    // masking of a single direction
+    pub fn g1(&self, w: Vec3) -> f64 {
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    // height correlated masking and shadowing
+    pub fn g(&self, wo: Vec3, wi: Vec3) -> f64 {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    // samples a normal from the distribution of normals visible from wo (heitz 2018)
+    pub fn sample_visible(&self, wo: Vec3, u1: f64, u2: f64) -> Vec3 {
+        // flip wo so that the sample comes from the upper hemisphere
+        let wo = if wo.z < 0.0 { -wo } else { wo };
+
+        let vh = Vec3::new(self.alpha_x * wo.x, self.alpha_y * wo.y, wo.z).unit();
+        let length_squared = vh.x * vh.x + vh.y * vh.y;
+        let t1 = if length_squared > 0.0 {
+            Vec3::new(-vh.y, vh.x, 0.0) / length_squared.sqrt()
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let t2 = vh.cross(&t1);
+
+        let r = u1.sqrt();
+        let phi = 2.0 * PI * u2;
+        let p1 = r * phi.cos();
+        let s = 0.5 * (1.0 + vh.z);
+        let p2 = (1.0 - s) * (1.0 - p1 * p1).sqrt() + s * r * phi.sin();
+
+        let nh = t1 * p1 + t2 * p2 + vh * (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt();
+
+        Vec3::new(self.alpha_x * nh.x, self.alpha_y * nh.y, nh.z.max(1e-6)).unit()
+    }
+
+    // pdf of sample_visible returning m
+    pub fn pdf_visible(&self, wo: Vec3, m: Vec3) -> f64 {
+        if wo.z == 0.0 {
+            return 0.0;
+        }
+        self.g1(wo) * wo.dot(&m).abs() * self.d(m) / wo.z.abs()
+    }
+}
+
+// unpolarized fresnel reflectance of a conductor with complex index eta + ik,
+// evaluated per color channel
+pub fn fresnel_conductor(cos_theta_i: f64, eta: Vec3, k: Vec3) -> Vec3 {
+    let channel = |eta: f64, k: f64| {
+        let cos2 = cos_theta_i.clamp(-1.0, 1.0).powi(2);
+        let sin2 = 1.0 - cos2;
+        let eta2 = eta * eta;
+        let k2 = k * k;
+
+        let t0 = eta2 - k2 - sin2;
+        let a2_plus_b2 = (t0 * t0 + 4.0 * eta2 * k2).sqrt();
+        let t1 = a2_plus_b2 + cos2;
+        let a = (0.5 * (a2_plus_b2 + t0)).max(0.0).sqrt();
+        let t2 = 2.0 * cos_theta_i.abs() * a;
+        let rs = (t1 - t2) / (t1 + t2);
+
+        let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+        let t4 = t2 * sin2;
+        let rp = rs * (t3 - t4) / (t3 + t4);
+
+        0.5 * (rp + rs)
+    };
+
+    Vec3::new(
+        channel(eta.x, k.x),
+        channel(eta.y, k.y),
+        channel(eta.z, k.z),
+    )
+}
+
+// unpolarized fresnel reflectance of a dielectric interface, eta is the index on
+// the far side over the index on the incident side. a negative cosine means the
+// light arrives from the far side
+pub fn fresnel_dielectric(cos_theta_i: f64, eta: f64) -> f64 {
+    let mut cos_i = cos_theta_i.clamp(-1.0, 1.0);
+    let mut eta = eta;
+    if cos_i < 0.0 {
+        eta = 1.0 / eta;
+        cos_i = -cos_i;
+    }
+
+    let sin2_t = (1.0 - cos_i * cos_i) / (eta * eta);
+    if sin2_t >= 1.0 {
+        // total internal reflection
+        return 1.0;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+
+    let r_parallel = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let r_perpendicular = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+
+    0.5 * (r_parallel * r_parallel + r_perpendicular * r_perpendicular)
+}
+
+// refracts wo (pointing away from the surface) through the interface with normal m,
+// None on total internal reflection
+pub fn refract(wo: Vec3, m: Vec3, eta: f64) -> Option<Vec3> {
+    let cos_i = wo.dot(&m);
+    let sin2_t = (1.0 - cos_i * cos_i).max(0.0) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return None;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+
+    Some(-wo / eta + m * (cos_i / eta - cos_t))
+}
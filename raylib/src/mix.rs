@@ -0,0 +1,190 @@
+use crate::hittable::Hit;
+use crate::material::Material;
+use crate::medium::Medium;
+use crate::random;
+use crate::ray::Ray;
+use crate::spectrum::{Color, SampledSpectrum, Wavelengths};
+use crate::texture::{SolidColor, Texture};
+use crate::vec::Vec3;
+
+// blend of two materials, like worn paint over metal. each scatter picks one of
+// them by the weight at the hit, 0 being all first and 1 all second
+pub struct Mix {
+    pub first: Box<dyn Material>,
+    pub second: Box<dyn Material>,
+    pub weight: Box<dyn Texture>,
+}
+
+impl Mix {
+    pub fn new(first: Box<dyn Material>, second: Box<dyn Material>, weight: f64) -> Mix {
+        Mix::textured(
+            first,
+            second,
+            Box::new(SolidColor::new(Vec3::new(weight, weight, weight))),
+        )
+    }
+
+    pub fn textured(
+        first: Box<dyn Material>,
+        second: Box<dyn Material>,
+        weight: Box<dyn Texture>,
+    ) -> Mix {
+        Mix {
+            first,
+            second,
+            weight,
+        }
+    }
+
+    fn weight(&self, hit: &Hit) -> f64 {
+        self.weight
+            .value(hit.u, hit.v, hit.point)
+            .average()
+            .clamp(0.0, 1.0)
+    }
+
+    // the material u picks by the weight, with u stretched back over the unit
+    // interval for the picked material's own lobe choice
+    fn pick(&self, hit: &Hit, u: f64) -> (&dyn Material, f64) {
+        let weight = self.weight(hit);
+        if u < weight {
+            (self.second.as_ref(), u / weight)
+        } else {
+            (self.first.as_ref(), (u - weight) / (1.0 - weight))
+        }
+    }
+}
+
+// the blended bsdf and the pdf of sampling it through either material
+fn blend<C: Color>(
+    weight: f64,
+    first: Option<(C, f64)>,
+    second: Option<(C, f64)>,
+) -> Option<(C, f64)> {
+    if first.is_none() && second.is_none() {
+        return None;
+    }
+    let (first_f, first_pdf) = first.unwrap_or((C::splat(0.0), 0.0));
+    let (second_f, second_pdf) = second.unwrap_or((C::splat(0.0), 0.0));
+
+    Some((
+        first_f * (1.0 - weight) + second_f * weight,
+        first_pdf * (1.0 - weight) + second_pdf * weight,
+    ))
+}
+
+// a non-specular sample is weighted against both materials, otherwise the picked
+// material's attenuation already accounts for the choice
+fn reweight<C: Color>(
+    scattered: Ray,
+    attenuation: C,
+    pdf: Option<f64>,
+    blended: Option<(C, f64)>,
+) -> Option<(Ray, C, Option<f64>)> {
+    match (pdf, blended) {
+        (Some(_), Some((f, pdf))) if pdf > 0.0 => Some((scattered, f * (1.0 / pdf), Some(pdf))),
+        _ => Some((scattered, attenuation, pdf)),
+    }
+}
+
+impl Material for Mix {
+    fn scatter(&self, ray: &Ray, hit: Hit, rng: &mut rand::rngs::ThreadRng) -> Option<(Ray, Vec3)> {
+        self.scatter_with_pdf(ray, hit, rng)
+            .map(|(scattered, attenuation, _)| (scattered, attenuation))
+    }
+
+    fn scatter_with_pdf(
+        &self,
+        ray: &Ray,
+        hit: Hit,
+        rng: &mut rand::rngs::ThreadRng,
+    ) -> Option<(Ray, Vec3, Option<f64>)> {
+        self.scatter_sampled(ray, hit, random(rng), (random(rng), random(rng)), rng)
+    }
+
+    fn scatter_sampled(
+        &self,
+        ray: &Ray,
+        hit: Hit,
+        u_lobe: f64,
+        u: (f64, f64),
+        rng: &mut rand::rngs::ThreadRng,
+    ) -> Option<(Ray, Vec3, Option<f64>)> {
+        let (material, u_lobe) = self.pick(&hit, u_lobe);
+        let (scattered, attenuation, pdf) = material.scatter_sampled(ray, hit, u_lobe, u, rng)?;
+        let blended = match pdf {
+            Some(_) => self.eval(ray, &hit, scattered.direction),
+            None => None,
+        };
+
+        reweight(scattered, attenuation, pdf, blended)
+    }
+
+    fn eval(&self, ray: &Ray, hit: &Hit, direction: Vec3) -> Option<(Vec3, f64)> {
+        blend(
+            self.weight(hit),
+            self.first.eval(ray, hit, direction),
+            self.second.eval(ray, hit, direction),
+        )
+    }
+
+    fn scatter_spectral(
+        &self,
+        ray: &Ray,
+        hit: Hit,
+        wavelengths: &mut Wavelengths,
+        rng: &mut rand::rngs::ThreadRng,
+    ) -> Option<(Ray, SampledSpectrum, Option<f64>)> {
+        let (u_lobe, u) = (random(rng), (random(rng), random(rng)));
+        self.scatter_spectral_sampled(ray, hit, wavelengths, u_lobe, u, rng)
+    }
+
+    fn scatter_spectral_sampled(
+        &self,
+        ray: &Ray,
+        hit: Hit,
+        wavelengths: &mut Wavelengths,
+        u_lobe: f64,
+        u: (f64, f64),
+        rng: &mut rand::rngs::ThreadRng,
+    ) -> Option<(Ray, SampledSpectrum, Option<f64>)> {
+        let (material, u_lobe) = self.pick(&hit, u_lobe);
+        let (scattered, attenuation, pdf) =
+            material.scatter_spectral_sampled(ray, hit, wavelengths, u_lobe, u, rng)?;
+        let blended = match pdf {
+            Some(_) => self.eval_spectral(ray, &hit, scattered.direction, wavelengths),
+            None => None,
+        };
+
+        reweight(scattered, attenuation, pdf, blended)
+    }
+
+    fn eval_spectral(
+        &self,
+        ray: &Ray,
+        hit: &Hit,
+        direction: Vec3,
+        wavelengths: &Wavelengths,
+    ) -> Option<(SampledSpectrum, f64)> {
+        blend(
+            self.weight(hit),
+            self.first.eval_spectral(ray, hit, direction, wavelengths),
+            self.second.eval_spectral(ray, hit, direction, wavelengths),
+        )
+    }
+
+    fn emitted(&self, hit: &Hit) -> Vec3 {
+        let weight = self.weight(hit);
+        self.first.emitted(hit) * (1.0 - weight) + self.second.emitted(hit) * weight
+    }
+
+    // a medium can't be blended, so the first material's wins when both have one
+    fn interior(&self) -> Option<&dyn Medium> {
+        self.first.interior().or_else(|| self.second.interior())
+    }
+
+    fn alpha(&self, hit: &Hit) -> f64 {
+        let weight = self.weight(hit);
+        self.first.alpha(hit) * (1.0 - weight) + self.second.alpha(hit) * weight
+    }
+}
@@ -0,0 +1,54 @@
+use crate::vec::Vec3;
+
+// orthonormal basis around w, used to move directions in and out of a local
+// frame where w is "up"
+#[derive(Copy, Clone, Debug)]
+pub struct Onb {
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+}
+
+impl Onb {
+    pub fn from_w(w: Vec3) -> Onb {
+        // branchless construction (duff et al. 2017)
+        let w = w.unit();
+        let sign = 1.0_f64.copysign(w.z);
+        let a = -1.0 / (sign + w.z);
+        let b = w.x * w.y * a;
+        let u = Vec3::new(1.0 + sign * w.x * w.x * a, sign * b, -sign * w.x);
+        let v = Vec3::new(b, sign + w.y * w.y * a, -w.y);
+
+        Onb { u, v, w }
+    }
+
+    // basis around w with u along the part of tangent perpendicular to w, so
+    // anisotropic surfaces follow their parameterization. without a usable
+    // tangent it's the same as from_w
+    pub fn from_w_tangent(w: Vec3, tangent: Vec3) -> Onb {
+        let w = w.unit();
+        let u = tangent - w * w.dot(&tangent);
+        if tangent.near_zero() || u.near_zero() {
+            return Onb::from_w(w);
+        }
+        let u = u.unit();
+
+        Onb {
+            u,
+            v: w.cross(&u),
+            w,
+        }
+    }
+
+    pub fn local(&self, a: f64, b: f64, c: f64) -> Vec3 {
+        self.u * a + self.v * b + self.w * c
+    }
+
+    pub fn to_local(&self, direction: Vec3) -> Vec3 {
+        Vec3::new(
+            direction.dot(&self.u),
+            direction.dot(&self.v),
+            direction.dot(&self.w),
+        )
+    }
+}
@@ -0,0 +1,245 @@
+use crate::hittable::Hit;
+use crate::material::Material;
+use crate::medium::Medium;
+use crate::onb::Onb;
+use crate::ray::Ray;
+use crate::spectrum::{Color, SampledSpectrum, Wavelengths};
+use crate::texture::Texture;
+use crate::vec::Vec3;
+
+// step along u and v for the finite differences of a bump map
+const BUMP_DELTA: f64 = 0.0005;
+
+pub enum Perturbation {
+    // tangent space normals encoded as rgb in [0, 1], green pointing along +v
+    NormalMap {
+        map: Box<dyn Texture>,
+        strength: f64,
+    },
+    // heights in world units, the average of the texture's channels times scale
+    BumpMap {
+        height: Box<dyn Texture>,
+        scale: f64,
+    },
+}
+
+// another material shaded with a normal bent by a normal or bump map, which
+// fakes detail the geometry doesn't have. the geometric normal still decides
+// which side of the surface a direction is on, directions the two normals
+// disagree about are dropped so that light doesn't leak through the surface
+pub struct Perturbed {
+    pub material: Box<dyn Material>,
+    pub perturbation: Perturbation,
+}
+
+impl Perturbed {
+    pub fn normal_map(material: Box<dyn Material>, map: Box<dyn Texture>) -> Perturbed {
+        Perturbed {
+            material,
+            perturbation: Perturbation::NormalMap { map, strength: 1.0 },
+        }
+    }
+
+    pub fn bump_map(
+        material: Box<dyn Material>,
+        height: Box<dyn Texture>,
+        scale: f64,
+    ) -> Perturbed {
+        Perturbed {
+            material,
+            perturbation: Perturbation::BumpMap { height, scale },
+        }
+    }
+
+    fn perturbed_normal(&self, hit: &Hit) -> Vec3 {
+        let n = hit.normal;
+        if hit.dpdu.near_zero() || hit.dpdv.near_zero() {
+            return n;
+        }
+
+        match &self.perturbation {
+            Perturbation::NormalMap { map, strength } => {
+                let rgb = map.value(hit.u, hit.v, hit.point);
+                let x = (2.0 * rgb.x - 1.0) * strength;
+                let y = (2.0 * rgb.y - 1.0) * strength;
+                let z = (2.0 * rgb.z - 1.0).max(0.0);
+
+                let tangent = hit.dpdu - n * n.dot(&hit.dpdu);
+                if tangent.near_zero() {
+                    return n;
+                }
+                let tangent = tangent.unit();
+                let mut bitangent = n.cross(&tangent);
+                if bitangent.dot(&hit.dpdv) < 0.0 {
+                    bitangent = -bitangent;
+                }
+                let frame = Onb {
+                    u: tangent,
+                    v: bitangent,
+                    w: n,
+                };
+
+                frame.local(x, y, z).unit()
+            }
+            Perturbation::BumpMap { height, scale } => {
+                let displacement =
+                    |u: f64, v: f64, point: Vec3| height.value(u, v, point).average() * scale;
+                let h = displacement(hit.u, hit.v, hit.point);
+                let h_u =
+                    displacement(hit.u + BUMP_DELTA, hit.v, hit.point + hit.dpdu * BUMP_DELTA);
+                let h_v =
+                    displacement(hit.u, hit.v + BUMP_DELTA, hit.point + hit.dpdv * BUMP_DELTA);
+
+                // partials of the displaced surface, ignoring the normal's own change
+                let dpdu = hit.dpdu + n * ((h_u - h) / BUMP_DELTA);
+                let dpdv = hit.dpdv + n * ((h_v - h) / BUMP_DELTA);
+                let bumped = dpdu.cross(&dpdv).unit();
+
+                if bumped.dot(&n) < 0.0 {
+                    -bumped
+                } else {
+                    bumped
+                }
+            }
+        }
+    }
+
+    // the hit as the wrapped material sees it, keeping the geometric normal when
+    // the bent one would face away from the incoming ray
+    fn shading<'world>(&self, ray: &Ray, hit: &Hit<'world>) -> Hit<'world> {
+        let normal = self.perturbed_normal(hit);
+        if normal.dot(&ray.direction) >= 0.0 {
+            return *hit;
+        }
+
+        Hit { normal, ..*hit }
+    }
+
+    fn leaks(hit: &Hit, shading: &Hit, direction: Vec3) -> bool {
+        direction.dot(&hit.geometric_normal) * direction.dot(&shading.normal) <= 0.0
+    }
+}
+
+impl Material for Perturbed {
+    fn scatter(&self, ray: &Ray, hit: Hit, rng: &mut rand::rngs::ThreadRng) -> Option<(Ray, Vec3)> {
+        let shading = self.shading(ray, &hit);
+        let (scattered, attenuation) = self.material.scatter(ray, shading, rng)?;
+        if Perturbed::leaks(&hit, &shading, scattered.direction) {
+            return None;
+        }
+
+        Some((scattered, attenuation))
+    }
+
+    fn eval(&self, ray: &Ray, hit: &Hit, direction: Vec3) -> Option<(Vec3, f64)> {
+        let shading = self.shading(ray, hit);
+        let (f, pdf) = self.material.eval(ray, &shading, direction)?;
+        if Perturbed::leaks(hit, &shading, direction) {
+            return Some((Vec3::new(0.0, 0.0, 0.0), pdf));
+        }
+
+        Some((f, pdf))
+    }
+
+    fn scatter_with_pdf(
+        &self,
+        ray: &Ray,
+        hit: Hit,
+        rng: &mut rand::rngs::ThreadRng,
+    ) -> Option<(Ray, Vec3, Option<f64>)> {
+        let shading = self.shading(ray, &hit);
+        let scattered = self.material.scatter_with_pdf(ray, shading, rng)?;
+        if Perturbed::leaks(&hit, &shading, scattered.0.direction) {
+            return None;
+        }
+
+        Some(scattered)
+    }
+
+    fn scatter_sampled(
+        &self,
+        ray: &Ray,
+        hit: Hit,
+        u_lobe: f64,
+        u: (f64, f64),
+        rng: &mut rand::rngs::ThreadRng,
+    ) -> Option<(Ray, Vec3, Option<f64>)> {
+        let shading = self.shading(ray, &hit);
+        let scattered = self
+            .material
+            .scatter_sampled(ray, shading, u_lobe, u, rng)?;
+        if Perturbed::leaks(&hit, &shading, scattered.0.direction) {
+            return None;
+        }
+
+        Some(scattered)
+    }
+
+    fn scatter_spectral(
+        &self,
+        ray: &Ray,
+        hit: Hit,
+        wavelengths: &mut Wavelengths,
+        rng: &mut rand::rngs::ThreadRng,
+    ) -> Option<(Ray, SampledSpectrum, Option<f64>)> {
+        let shading = self.shading(ray, &hit);
+        let scattered = self
+            .material
+            .scatter_spectral(ray, shading, wavelengths, rng)?;
+        if Perturbed::leaks(&hit, &shading, scattered.0.direction) {
+            return None;
+        }
+
+        Some(scattered)
+    }
+
+    fn scatter_spectral_sampled(
+        &self,
+        ray: &Ray,
+        hit: Hit,
+        wavelengths: &mut Wavelengths,
+        u_lobe: f64,
+        u: (f64, f64),
+        rng: &mut rand::rngs::ThreadRng,
+    ) -> Option<(Ray, SampledSpectrum, Option<f64>)> {
+        let shading = self.shading(ray, &hit);
+        let scattered =
+            self.material
+                .scatter_spectral_sampled(ray, shading, wavelengths, u_lobe, u, rng)?;
+        if Perturbed::leaks(&hit, &shading, scattered.0.direction) {
+            return None;
+        }
+
+        Some(scattered)
+    }
+
+    fn eval_spectral(
+        &self,
+        ray: &Ray,
+        hit: &Hit,
+        direction: Vec3,
+        wavelengths: &Wavelengths,
+    ) -> Option<(SampledSpectrum, f64)> {
+        let shading = self.shading(ray, hit);
+        let (f, pdf) = self
+            .material
+            .eval_spectral(ray, &shading, direction, wavelengths)?;
+        if Perturbed::leaks(hit, &shading, direction) {
+            return Some((SampledSpectrum::new(0.0), pdf));
+        }
+
+        Some((f, pdf))
+    }
+
+    fn emitted(&self, hit: &Hit) -> Vec3 {
+        self.material.emitted(hit)
+    }
+
+    fn interior(&self) -> Option<&dyn Medium> {
+        self.material.interior()
+    }
+
+    fn alpha(&self, hit: &Hit) -> f64 {
+        self.material.alpha(hit)
+    }
+}
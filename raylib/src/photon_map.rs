@@ -0,0 +1,110 @@
+use crate::vec::Vec3;
+
+// light arriving at a surface, carrying its share of the lights' power
+#[derive(Copy, Clone)]
+pub struct Photon {
+    pub point: Vec3,
+    // unit direction the photon came from
+    pub direction: Vec3,
+    pub power: Vec3,
+}
+
+fn coordinate(v: Vec3, axis: usize) -> f64 {
+    match axis {
+        0 => v.x,
+        1 => v.y,
+        _ => v.z,
+    }
+}
+
+// balanced kd-tree over photons (jensen, 2001). it lives in place in the photon
+// list: the median of every range is the node splitting it, along the axis
+// stored for that index
+pub struct PhotonMap {
+    pub photons: std::vec::Vec<Photon>,
+    axes: std::vec::Vec<usize>,
+}
+
+impl PhotonMap {
+    pub fn new(photons: std::vec::Vec<Photon>) -> PhotonMap {
+        let mut photons = photons;
+        let mut axes = vec![0; photons.len()];
+        PhotonMap::build(&mut photons, &mut axes);
+
+        PhotonMap { photons, axes }
+    }
+
+    fn build(photons: &mut [Photon], axes: &mut [usize]) {
+        if photons.len() <= 1 {
+            return;
+        }
+
+        // split along the widest extent of the range
+        let mut min = photons[0].point;
+        let mut max = photons[0].point;
+        for photon in photons.iter() {
+            let p = photon.point;
+            min = Vec3::new(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z));
+            max = Vec3::new(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z));
+        }
+        let size = max - min;
+        let axis = if size.x >= size.y && size.x >= size.z {
+            0
+        } else if size.y >= size.z {
+            1
+        } else {
+            2
+        };
+
+        let median = photons.len() / 2;
+        photons.select_nth_unstable_by(median, |a, b| {
+            coordinate(a.point, axis).total_cmp(&coordinate(b.point, axis))
+        });
+        axes[median] = axis;
+
+        let (left, right) = photons.split_at_mut(median);
+        let (left_axes, right_axes) = axes.split_at_mut(median);
+        PhotonMap::build(left, left_axes);
+        PhotonMap::build(&mut right[1..], &mut right_axes[1..]);
+    }
+
+    // calls f with every photon within radius of the point
+    pub fn gather(&self, point: Vec3, radius: f64, f: &mut impl FnMut(&Photon)) {
+        self.search(0, self.photons.len(), point, radius * radius, f);
+    }
+
+    fn search(
+        &self,
+        start: usize,
+        end: usize,
+        point: Vec3,
+        radius_squared: f64,
+        f: &mut impl FnMut(&Photon),
+    ) {
+        if start >= end {
+            return;
+        }
+        let median = start + (end - start) / 2;
+        let photon = &self.photons[median];
+        if (photon.point - point).length_squared() <= radius_squared {
+            f(photon);
+        }
+        if end - start == 1 {
+            return;
+        }
+
+        // the side the point is on first, the other only if the sphere crosses
+        // the splitting plane
+        let axis = self.axes[median];
+        let delta = coordinate(point, axis) - coordinate(photon.point, axis);
+        let (near, far) = if delta < 0.0 {
+            ((start, median), (median + 1, end))
+        } else {
+            ((median + 1, end), (start, median))
+        };
+        self.search(near.0, near.1, point, radius_squared, f);
+        if delta * delta <= radius_squared {
+            self.search(far.0, far.1, point, radius_squared, f);
+        }
+    }
+}
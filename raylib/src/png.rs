@@ -0,0 +1,83 @@
+use std::fs;
+use std::path::Path;
+
+// crc-32 as png chunks use it, bit by bit since only whole frames go through it
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in bytes.chunks(5552) {
+        for byte in chunk {
+            a += *byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
+
+fn chunk(png: &mut std::vec::Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+// an 8 bit rgb png of the rows top first, three bytes a pixel. the image data
+// is stored in uncompressed deflate blocks, which every decoder reads and needs
+// no compressor
+pub fn write_png(path: &str, width: usize, height: usize, rgb: &[u8]) {
+    // png has no empty images
+    if width == 0 || height == 0 {
+        panic!("error png of {} x {} pixels is empty", width, height);
+    }
+    if rgb.len() != width * height * 3 {
+        panic!("error png expects {} bytes of rgb", width * height * 3);
+    }
+
+    let mut header = std::vec::Vec::new();
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    // 8 bits per channel, rgb, deflate, no filtering, not interlaced
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+    // every row starts with its filter type, none
+    let mut raw = std::vec::Vec::with_capacity((width * 3 + 1) * height);
+    for row in rgb.chunks(width * 3) {
+        raw.push(0);
+        raw.extend_from_slice(row);
+    }
+
+    let mut data = vec![0x78, 0x01];
+    let blocks: std::vec::Vec<&[u8]> = raw.chunks(65535).collect();
+    for (i, block) in blocks.iter().enumerate() {
+        data.push(if i + 1 == blocks.len() { 1 } else { 0 });
+        let length = block.len() as u16;
+        data.extend_from_slice(&length.to_le_bytes());
+        data.extend_from_slice(&(!length).to_le_bytes());
+        data.extend_from_slice(block);
+    }
+    data.extend_from_slice(&adler32(&raw).to_be_bytes());
+
+    let mut png = vec![0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+    chunk(&mut png, b"IHDR", &header);
+    chunk(&mut png, b"IDAT", &data);
+    chunk(&mut png, b"IEND", &[]);
+
+    if let Err(err) = fs::write(Path::new(path), png) {
+        panic!("error {}", err);
+    }
+}
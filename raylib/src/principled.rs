@@ -0,0 +1,410 @@
+use crate::hittable::Hit;
+use crate::material::{upsampled, Material};
+use crate::microfacet::Ggx;
+use crate::onb::Onb;
+use crate::random;
+use crate::ray::Ray;
+use crate::rough_dielectric::RoughDielectric;
+use crate::spectrum::{SampledSpectrum, Wavelengths};
+use crate::vec::Vec3;
+
+use std::f64::consts::PI;
+
+// smallest alpha the lobes use, so that every lobe can be evaluated
+const MIN_ALPHA: f64 = 0.002;
+
+fn lerp(a: Vec3, b: Vec3, t: f64) -> Vec3 {
+    a * (1.0 - t) + b * t
+}
+
+fn schlick_weight(cos_theta: f64) -> f64 {
+    (1.0 - cos_theta.clamp(0.0, 1.0)).powi(5)
+}
+
+// hue and saturation of a color, without its luminance
+fn tint(color: Vec3) -> Vec3 {
+    let luminance = color.luminance();
+    if luminance > 0.0 {
+        color / luminance
+    } else {
+        Vec3::new(1.0, 1.0, 1.0)
+    }
+}
+
+// generalized trowbridge-reitz with gamma 1, the long tailed clearcoat highlight
+fn gtr1(cos_h: f64, alpha: f64) -> f64 {
+    let a2 = alpha * alpha;
+    (a2 - 1.0) / (PI * a2.ln() * (1.0 + (a2 - 1.0) * cos_h * cos_h))
+}
+
+// disney's principled bsdf (burley 2012, 2015): one material driven by the
+// parameters artists use in their dcc tools, all in [0, 1] apart from the ior
+#[derive(Clone)]
+pub struct Principled {
+    pub base_color: Vec3,
+    pub metallic: f64,
+    pub roughness: f64,
+    // stretches the highlight along the surface's u direction, dpdu
+    pub anisotropic: f64,
+    pub specular: f64,
+    pub specular_tint: f64,
+    pub sheen: f64,
+    pub sheen_tint: f64,
+    pub clearcoat: f64,
+    pub clearcoat_gloss: f64,
+    pub transmission: f64,
+    pub index_of_refraction: f64,
+}
+
+// bsdf times cosine and pdf of each lobe for one pair of directions
+struct Lobes {
+    f: Vec3,
+    pdf: f64,
+}
+
+impl Principled {
+    // a dielectric with the default specular of 0.5 (ior 1.5)
+    pub fn new(base_color: Vec3, roughness: f64) -> Principled {
+        Principled {
+            base_color,
+            metallic: 0.0,
+            roughness,
+            anisotropic: 0.0,
+            specular: 0.5,
+            specular_tint: 0.0,
+            sheen: 0.0,
+            sheen_tint: 0.5,
+            clearcoat: 0.0,
+            clearcoat_gloss: 1.0,
+            transmission: 0.0,
+            index_of_refraction: 1.5,
+        }
+    }
+
+    pub fn metal(base_color: Vec3, roughness: f64) -> Principled {
+        Principled {
+            metallic: 1.0,
+            ..Principled::new(base_color, roughness)
+        }
+    }
+
+    pub fn glass(base_color: Vec3, roughness: f64, index_of_refraction: f64) -> Principled {
+        Principled {
+            transmission: 1.0,
+            index_of_refraction,
+            ..Principled::new(base_color, roughness)
+        }
+    }
+
+    fn specular_distribution(&self) -> Ggx {
+        let aspect = (1.0 - 0.9 * self.anisotropic.clamp(0.0, 1.0)).sqrt();
+        let alpha = self.roughness.clamp(0.0, 1.0).powi(2);
+
+        Ggx {
+            alpha_x: (alpha / aspect).max(MIN_ALPHA),
+            alpha_y: (alpha * aspect).max(MIN_ALPHA),
+        }
+    }
+
+    fn glass_lobe(&self) -> RoughDielectric {
+        let alpha = self.roughness.clamp(0.0, 1.0).powi(2).max(MIN_ALPHA);
+        RoughDielectric {
+            index_of_refraction: self.index_of_refraction,
+            distribution: Ggx {
+                alpha_x: alpha,
+                alpha_y: alpha,
+            },
+            absorption: None,
+        }
+    }
+
+    fn clearcoat_alpha(&self) -> f64 {
+        0.1 * (1.0 - self.clearcoat_gloss) + 0.001 * self.clearcoat_gloss
+    }
+
+    // color of the specular reflection at normal incidence
+    fn specular_color(&self) -> Vec3 {
+        let white = Vec3::new(1.0, 1.0, 1.0);
+        let dielectric =
+            lerp(white, tint(self.base_color), self.specular_tint) * (self.specular * 0.08);
+
+        lerp(dielectric, self.base_color, self.metallic)
+    }
+
+    // weights of the diffuse, specular, clearcoat and glass lobes
+    fn lobe_weights(&self) -> [f64; 4] {
+        let dielectric = 1.0 - self.metallic;
+        [
+            dielectric * (1.0 - self.transmission),
+            1.0 - dielectric * self.transmission,
+            0.25 * self.clearcoat,
+            dielectric * self.transmission,
+        ]
+    }
+
+    // chance of sampling each lobe, roughly in proportion to what it reflects
+    fn lobe_probabilities(&self, cos_o: f64) -> [f64; 4] {
+        let weights = self.lobe_weights();
+        let fresnel = schlick_weight(cos_o);
+        let specular = lerp(self.specular_color(), Vec3::new(1.0, 1.0, 1.0), fresnel);
+
+        let mut p = [
+            weights[0] * self.base_color.luminance().max(0.01),
+            weights[1] * specular.luminance().max(0.01),
+            weights[2] * (0.04 + 0.96 * fresnel),
+            weights[3],
+        ];
+        let total: f64 = p.iter().sum();
+        if total > 0.0 {
+            for p in p.iter_mut() {
+                *p /= total;
+            }
+        }
+        p
+    }
+
+    // back faces of transmissive surfaces are seen from inside the glass, those
+    // of opaque ones (open meshes, planes) reflect like the front
+    fn inside_glass(&self, hit: &Hit) -> bool {
+        !hit.front_face && self.lobe_weights()[3] > 0.0
+    }
+
+    fn eval_lobes(&self, ray: &Ray, hit: &Hit, direction: Vec3) -> Lobes {
+        let black = Lobes {
+            f: Vec3::new(0.0, 0.0, 0.0),
+            pdf: 0.0,
+        };
+
+        // from inside only the glass interface is left
+        if self.inside_glass(hit) {
+            return match self.glass_lobe().eval(ray, hit, direction) {
+                Some((f, pdf)) => Lobes {
+                    f: self.tint_transmission(f, hit, direction),
+                    pdf,
+                },
+                None => black,
+            };
+        }
+
+        let frame = Onb::from_w_tangent(hit.normal, hit.dpdu);
+        let wo = frame.to_local(-ray.direction.unit());
+        let wi = frame.to_local(direction.unit());
+        if wo.z <= 0.0 || wi.z == 0.0 {
+            return black;
+        }
+        let weights = self.lobe_weights();
+        let p = self.lobe_probabilities(wo.z);
+
+        let mut f = Vec3::new(0.0, 0.0, 0.0);
+        let mut pdf = 0.0;
+
+        if weights[3] > 0.0 {
+            if let Some((glass_f, glass_pdf)) = self.glass_lobe().eval(ray, hit, direction) {
+                f = f + self.tint_transmission(glass_f, hit, direction) * weights[3];
+                pdf += p[3] * glass_pdf;
+            }
+        }
+        if wi.z < 0.0 {
+            return Lobes { f, pdf };
+        }
+
+        let h = (wo + wi).unit();
+        let cos_d = wi.dot(&h);
+        let fresnel_d = schlick_weight(cos_d);
+
+        if weights[0] > 0.0 {
+            // burley diffuse with retro-reflection at grazing angles, plus sheen
+            let fd90 = 0.5 + 2.0 * self.roughness * cos_d * cos_d;
+            let retro = (1.0 + (fd90 - 1.0) * schlick_weight(wi.z))
+                * (1.0 + (fd90 - 1.0) * schlick_weight(wo.z));
+            let sheen_color = lerp(
+                Vec3::new(1.0, 1.0, 1.0),
+                tint(self.base_color),
+                self.sheen_tint,
+            );
+            let sheen = sheen_color * (self.sheen * fresnel_d);
+
+            f = f + (self.base_color * (retro / PI) + sheen) * (weights[0] * wi.z);
+            pdf += p[0] * wi.z / PI;
+        }
+
+        if weights[1] > 0.0 {
+            let distribution = self.specular_distribution();
+            let d = distribution.d(h);
+            let specular = lerp(self.specular_color(), Vec3::new(1.0, 1.0, 1.0), fresnel_d);
+
+            f = f + specular * (weights[1] * d * distribution.g(wo, wi) / (4.0 * wo.z));
+            pdf += p[1] * distribution.g1(wo) * d / (4.0 * wo.z);
+        }
+
+        if weights[2] > 0.0 {
+            let d = gtr1(h.z, self.clearcoat_alpha());
+            let coat = Ggx {
+                alpha_x: 0.25,
+                alpha_y: 0.25,
+            };
+            let fresnel = 0.04 + 0.96 * fresnel_d;
+            let g = coat.g1(wo) * coat.g1(wi);
+            let value = weights[2] * d * fresnel * g / (4.0 * wo.z);
+
+            f = f + Vec3::new(value, value, value);
+            pdf += p[2] * d * h.z / (4.0 * wo.dot(&h));
+        }
+
+        Lobes { f, pdf }
+    }
+
+    // a path through the glass crosses it twice, the base color tints it once on
+    // the way in
+    fn tint_transmission(&self, f: Vec3, hit: &Hit, direction: Vec3) -> Vec3 {
+        if hit.front_face && direction.dot(&hit.normal) < 0.0 {
+            f * self.base_color
+        } else {
+            f
+        }
+    }
+
+    // u_lobe picks the lobe and u the direction in it, the glass lobe draws its
+    // own from rng
+    fn sample_direction(
+        &self,
+        ray: &Ray,
+        hit: &Hit,
+        u_lobe: f64,
+        u: (f64, f64),
+        rng: &mut rand::rngs::ThreadRng,
+    ) -> Option<Vec3> {
+        if self.inside_glass(hit) {
+            return self
+                .glass_lobe()
+                .scatter(ray, *hit, rng)
+                .map(|(scattered, _)| scattered.direction);
+        }
+
+        let frame = Onb::from_w_tangent(hit.normal, hit.dpdu);
+        let wo = frame.to_local(-ray.direction.unit());
+        if wo.z <= 0.0 {
+            return None;
+        }
+        let p = self.lobe_probabilities(wo.z);
+
+        let wi = if u_lobe < p[0] {
+            // cosine weighted hemisphere
+            let r = u.0.sqrt();
+            let phi = 2.0 * PI * u.1;
+            Vec3::new(r * phi.cos(), r * phi.sin(), (1.0 - r * r).max(0.0).sqrt())
+        } else if u_lobe < p[0] + p[1] {
+            let m = self.specular_distribution().sample_visible(wo, u.0, u.1);
+            m * (2.0 * wo.dot(&m)) - wo
+        } else if u_lobe < p[0] + p[1] + p[2] {
+            let a2 = self.clearcoat_alpha().powi(2);
+            let cos_h = ((1.0 - a2.powf(1.0 - u.0)) / (1.0 - a2)).max(0.0).sqrt();
+            let sin_h = (1.0 - cos_h * cos_h).max(0.0).sqrt();
+            let phi = 2.0 * PI * u.1;
+            let m = Vec3::new(sin_h * phi.cos(), sin_h * phi.sin(), cos_h);
+            m * (2.0 * wo.dot(&m)) - wo
+        } else {
+            return self
+                .glass_lobe()
+                .scatter(ray, *hit, rng)
+                .map(|(scattered, _)| scattered.direction);
+        };
+
+        Some(frame.local(wi.x, wi.y, wi.z))
+    }
+}
+
+impl Material for Principled {
+    fn scatter(&self, ray: &Ray, hit: Hit, rng: &mut rand::rngs::ThreadRng) -> Option<(Ray, Vec3)> {
+        self.scatter_with_pdf(ray, hit, rng)
+            .map(|(scattered, attenuation, _)| (scattered, attenuation))
+    }
+
+    // one lobe picks the direction, the weight accounts for all of them
+    fn scatter_with_pdf(
+        &self,
+        ray: &Ray,
+        hit: Hit,
+        rng: &mut rand::rngs::ThreadRng,
+    ) -> Option<(Ray, Vec3, Option<f64>)> {
+        self.scatter_sampled(ray, hit, random(rng), (random(rng), random(rng)), rng)
+    }
+
+    fn scatter_sampled(
+        &self,
+        ray: &Ray,
+        hit: Hit,
+        u_lobe: f64,
+        u: (f64, f64),
+        rng: &mut rand::rngs::ThreadRng,
+    ) -> Option<(Ray, Vec3, Option<f64>)> {
+        let direction = self.sample_direction(ray, &hit, u_lobe, u, rng)?;
+        let lobes = self.eval_lobes(ray, &hit, direction);
+        if lobes.pdf <= 0.0 {
+            return None;
+        }
+
+        Some((
+            Ray::new(hit.point, direction),
+            lobes.f / lobes.pdf,
+            Some(lobes.pdf),
+        ))
+    }
+
+    fn scatter_spectral_sampled(
+        &self,
+        ray: &Ray,
+        hit: Hit,
+        wavelengths: &mut Wavelengths,
+        u_lobe: f64,
+        u: (f64, f64),
+        rng: &mut rand::rngs::ThreadRng,
+    ) -> Option<(Ray, SampledSpectrum, Option<f64>)> {
+        upsampled(self.scatter_sampled(ray, hit, u_lobe, u, rng), wavelengths)
+    }
+
+    fn eval(&self, ray: &Ray, hit: &Hit, direction: Vec3) -> Option<(Vec3, f64)> {
+        let lobes = self.eval_lobes(ray, hit, direction);
+        Some((lobes.f, lobes.pdf))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::{white_furnace, RenderSettings};
+
+    fn furnace(material: Principled) -> f64 {
+        white_furnace(Box::new(material), &RenderSettings::new(8, 8, 64, 32))
+    }
+
+    #[test]
+    fn smooth_glass_and_metal_keep_energy() {
+        let white = Vec3::new(1.0, 1.0, 1.0);
+        let glass = furnace(Principled::glass(white, 0.0, 1.5));
+        assert!((glass - 1.0).abs() < 0.02, "glass furnace mean {}", glass);
+        let metal = furnace(Principled::metal(white, 0.0));
+        assert!((metal - 1.0).abs() < 0.02, "metal furnace mean {}", metal);
+    }
+
+    // microfacet lobes only model single scattering, so rough ones lose energy
+    // but never make any
+    #[test]
+    fn rough_lobes_lose_energy() {
+        let white = Vec3::new(1.0, 1.0, 1.0);
+        for roughness in [0.5, 1.0] {
+            let glass = furnace(Principled::glass(white, roughness, 1.5));
+            assert!(glass < 1.02, "glass furnace mean {}", glass);
+            let metal = furnace(Principled::metal(white, roughness));
+            assert!(metal < 1.02, "metal furnace mean {}", metal);
+        }
+    }
+
+    // light through a gray glass sphere comes out at half plus the white
+    // reflections, tinting on the way out too would take it to a quarter
+    #[test]
+    fn glass_is_tinted_once() {
+        let gray = furnace(Principled::glass(Vec3::new(0.5, 0.5, 0.5), 0.0, 1.5));
+        assert!(gray > 0.5 && gray < 0.7, "gray glass furnace mean {}", gray);
+    }
+}
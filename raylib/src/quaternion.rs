@@ -0,0 +1,81 @@
+use crate::vec::Vec3;
+
+// unit quaternion for rotations, which unlike matrices interpolate along the
+// shortest arc without shearing
+#[derive(Copy, Clone, Debug)]
+pub struct Quaternion {
+    pub w: f64,
+    pub v: Vec3,
+}
+
+impl Quaternion {
+    pub fn identity() -> Quaternion {
+        Quaternion {
+            w: 1.0,
+            v: Vec3::new(0.0, 0.0, 0.0),
+        }
+    }
+
+    // rotation by degrees counterclockwise around the axis, looking down it
+    pub fn from_axis_angle(axis: Vec3, degrees: f64) -> Quaternion {
+        let half = degrees.to_radians() / 2.0;
+        Quaternion {
+            w: half.cos(),
+            v: axis.unit() * half.sin(),
+        }
+    }
+
+    pub fn conjugate(&self) -> Quaternion {
+        Quaternion {
+            w: self.w,
+            v: -self.v,
+        }
+    }
+
+    fn dot(&self, other: &Quaternion) -> f64 {
+        self.w * other.w + self.v.dot(&other.v)
+    }
+
+    fn normalized(&self) -> Quaternion {
+        let length = self.dot(self).sqrt();
+        Quaternion {
+            w: self.w / length,
+            v: self.v / length,
+        }
+    }
+
+    pub fn rotate(&self, p: Vec3) -> Vec3 {
+        // q p q* expanded for a unit quaternion
+        let t = self.v.cross(&p) * 2.0;
+        p + t * self.w + self.v.cross(&t)
+    }
+
+    // spherical linear interpolation from a to b at the fraction t, the short
+    // way round
+    pub fn slerp(a: &Quaternion, b: &Quaternion, t: f64) -> Quaternion {
+        let mut b = *b;
+        let mut cos_theta = a.dot(&b);
+        if cos_theta < 0.0 {
+            b = Quaternion { w: -b.w, v: -b.v };
+            cos_theta = -cos_theta;
+        }
+
+        // nearly the same rotation, where the sines vanish
+        if cos_theta > 0.9995 {
+            let q = Quaternion {
+                w: a.w + (b.w - a.w) * t,
+                v: a.v + (b.v - a.v) * t,
+            };
+            return q.normalized();
+        }
+
+        let theta = cos_theta.acos();
+        let sin_theta = theta.sin();
+        let wa = ((1.0 - t) * theta).sin() / sin_theta;
+        let wb = (t * theta).sin() / sin_theta;
+        Quaternion {
+            w: a.w * wa + b.w * wb,
+            v: a.v * wa + b.v * wb,
+        }
+    }
+}
@@ -6,6 +6,9 @@ use crate::{random_in_hemisphere, random_in_unit_sphere, random_unit_vector};
 pub struct Ray {
     pub direction: Vec3,
     pub origin: Vec3,
+    // moment within the camera's shutter the ray is sent at, which animated
+    // objects are intersected at
+    pub time: f64,
 }
 
 impl Ray {
@@ -13,6 +16,15 @@ impl Ray {
         Ray {
             origin: origin,
             direction: direction,
+            time: 0.0,
+        }
+    }
+
+    pub fn with_time(origin: Vec3, direction: Vec3, time: f64) -> Ray {
+        Ray {
+            origin,
+            direction,
+            time,
         }
     }
 
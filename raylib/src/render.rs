@@ -0,0 +1,355 @@
+use crate::adaptive::{AdaptiveSampling, RunningVariance};
+use crate::aov::Aov;
+use crate::background::Background;
+use crate::bdpt::Bidirectional;
+use crate::camera::Camera;
+use crate::exposure::{auto_ev100, exposure_scale};
+use crate::hittable::Hittables;
+use crate::image::Image;
+use crate::integrator::{path_trace, path_trace_spectral};
+use crate::sampler::SamplerKind;
+use crate::spectrum::Wavelengths;
+use crate::sppm::PhotonMapping;
+use crate::vec::Vec3;
+use crate::xyz_to_rgb;
+
+pub enum Integrator {
+    PathTracer,
+    // bidirectional path tracing for caustics and other light the camera paths
+    // rarely find. always renders rgb
+    Bidirectional,
+    // stochastic progressive photon mapping, with one pass per sample. each pass
+    // shoots photons_per_pass photons and pixels start gathering them within
+    // radius, in world units. always renders rgb
+    PhotonMapping {
+        photons_per_pass: usize,
+        radius: f64,
+    },
+    // renders the aov instead of light
+    Aov(Aov),
+}
+
+pub struct RenderSettings {
+    pub width: usize,
+    pub height: usize,
+    pub samples_per_pixel: i32,
+    pub max_depth: i32,
+    // trace sampled wavelengths instead of rgb, needed for dispersion
+    pub spectral: bool,
+    pub integrator: Integrator,
+    // expose the image like a light meter would rather than by the camera's settings
+    pub auto_exposure: bool,
+    // where the pixel, lens, shutter and wavelength numbers of camera rays come
+    // from, and the path tracer's numbers for each bounce after them. the other
+    // integrators draw their own past the camera ray
+    pub sampler: SamplerKind,
+    // lets pixels stop before samples_per_pixel once they're smooth. only the
+    // path tracer stops early, the other integrators take every sample
+    pub adaptive: Option<AdaptiveSampling>,
+}
+
+impl RenderSettings {
+    pub fn new(
+        width: usize,
+        height: usize,
+        samples_per_pixel: i32,
+        max_depth: i32,
+    ) -> RenderSettings {
+        RenderSettings {
+            width,
+            height,
+            samples_per_pixel,
+            max_depth,
+            spectral: false,
+            integrator: Integrator::PathTracer,
+            auto_exposure: false,
+            sampler: SamplerKind::Independent,
+            adaptive: None,
+        }
+    }
+
+    // pixels the camera's s and t cross going from 0 to 1, one fewer than there
+    // are. a single row or column of pixels spans the whole range instead
+    pub fn film_span(&self) -> (f64, f64) {
+        (
+            (self.width as f64 - 1.0).max(1.0),
+            (self.height as f64 - 1.0).max(1.0),
+        )
+    }
+}
+
+// renders the world into a framebuffer of linear rgb, scaled by the camera's
+// exposure or, with auto exposure, by what a light meter would set
+pub fn render(
+    camera: &Camera,
+    world: &Hittables,
+    background: &dyn Background,
+    settings: &RenderSettings,
+    rng: &mut rand::rngs::ThreadRng,
+) -> Image {
+    render_with_sample_counts(camera, world, background, settings, rng).0
+}
+
+// render, along with how many samples each pixel took as a share of
+// samples_per_pixel in gray, to see where adaptive sampling spent them
+pub fn render_with_sample_counts(
+    camera: &Camera,
+    world: &Hittables,
+    background: &dyn Background,
+    settings: &RenderSettings,
+    rng: &mut rand::rngs::ThreadRng,
+) -> (Image, Image) {
+    let mut every_sample = Image::new(settings.width, settings.height);
+    for pixel in every_sample.pixels.iter_mut() {
+        *pixel = Vec3::new(1.0, 1.0, 1.0);
+    }
+    let (mut image, counts) = match settings.integrator {
+        Integrator::Aov(aov) => {
+            let image = render_aovs(camera, world, &[aov], settings, rng).remove(0);
+            return (image, every_sample);
+        }
+        Integrator::PhotonMapping {
+            photons_per_pass,
+            radius,
+        } => {
+            let photon_mapping = PhotonMapping::new(camera, world, background, settings, rng);
+            let image = photon_mapping.render(settings, photons_per_pass, radius, rng);
+            (image, every_sample)
+        }
+        _ => render_paths(camera, world, background, settings, rng),
+    };
+
+    let scale = if settings.auto_exposure {
+        exposure_scale(auto_ev100(&image))
+    } else {
+        camera.exposure
+    };
+    if scale != 1.0 {
+        for pixel in image.pixels.iter_mut() {
+            *pixel = *pixel * scale;
+        }
+    }
+    (image, counts)
+}
+
+// spectral renders accumulate cie xyz per pixel and convert to rgb once all
+// samples are in. adaptive sampling follows the running variance of each
+// pixel's luminance, y in xyz
+fn render_paths(
+    camera: &Camera,
+    world: &Hittables,
+    background: &dyn Background,
+    settings: &RenderSettings,
+    rng: &mut rand::rngs::ThreadRng,
+) -> (Image, Image) {
+    let width = settings.width;
+    let height = settings.height;
+    let (span_x, span_y) = settings.film_span();
+    let mut image = Image::new(width, height);
+    let mut counts = Image::new(width, height);
+
+    // light reaching the camera from bidirectional paths, landing on any pixel
+    let mut splats = Image::new(width, height);
+    let bidirectional = match settings.integrator {
+        Integrator::Bidirectional => {
+            Some(Bidirectional::new(camera, world, background, settings, rng))
+        }
+        _ => None,
+    };
+    let mut sampler = settings
+        .sampler
+        .create(settings.samples_per_pixel.max(1) as usize, rng);
+    // splats land on every pixel, so every pixel has to take the same samples
+    let adaptive = match settings.integrator {
+        Integrator::PathTracer => settings.adaptive,
+        _ => None,
+    };
+
+    for y in 0..height {
+        // image rows run top to bottom while the camera's t runs bottom to top
+        let h = height - 1 - y;
+        for x in 0..width {
+            let mut pixel_color = Vec3::new(0.0, 0.0, 0.0);
+            // every sample counts, including rays the projection doesn't make
+            let mut luminance = RunningVariance::new();
+            for i in 0..settings.samples_per_pixel {
+                if let Some(adaptive) = &adaptive {
+                    if adaptive.converged(&luminance) {
+                        break;
+                    }
+                }
+                sampler.start_pixel_sample(x, y, i as usize);
+                let (jitter_x, jitter_y) = sampler.get_2d(rng);
+                let u = (x as f64 + jitter_x) / span_x;
+                let v = (h as f64 + jitter_y) / span_y;
+                let lens = sampler.get_2d(rng);
+                let time = sampler.get_1d(rng);
+                let ray = match camera.generate_ray(u, v, lens, time, rng) {
+                    Some(ray) => ray,
+                    None => {
+                        luminance.add(0.0);
+                        continue;
+                    }
+                };
+
+                let color = if let Some(bidirectional) = &bidirectional {
+                    let (color, samples) = bidirectional.trace(&ray, rng);
+                    for (s, t, splat) in samples {
+                        let sx = ((s * span_x) as usize).min(width - 1);
+                        let sh = ((t * span_y) as usize).min(height - 1);
+                        let sy = height - 1 - sh;
+                        splats.set(sx, sy, splats.get(sx, sy) + splat);
+                    }
+                    color
+                } else if settings.spectral {
+                    let mut wavelengths = Wavelengths::sample(sampler.get_1d(rng));
+                    let radiance = path_trace_spectral(
+                        &ray,
+                        world,
+                        background,
+                        settings.max_depth,
+                        &mut wavelengths,
+                        sampler.as_mut(),
+                        rng,
+                    );
+                    radiance.to_xyz(&wavelengths)
+                } else {
+                    path_trace(
+                        &ray,
+                        world,
+                        background,
+                        settings.max_depth,
+                        sampler.as_mut(),
+                        rng,
+                    )
+                };
+                pixel_color = pixel_color + color;
+                luminance.add(if settings.spectral && bidirectional.is_none() {
+                    color.y
+                } else {
+                    color.luminance()
+                });
+            }
+
+            let mut color = pixel_color / luminance.count.max(1) as f64;
+            if settings.spectral && bidirectional.is_none() {
+                color = xyz_to_rgb(color);
+            }
+            image.set(x, y, color);
+            let share = luminance.count as f64 / settings.samples_per_pixel.max(1) as f64;
+            counts.set(x, y, Vec3::new(share, share, share));
+        }
+    }
+
+    if bidirectional.is_some() {
+        for (pixel, splat) in image.pixels.iter_mut().zip(splats.pixels.iter()) {
+            *pixel = *pixel + *splat / settings.samples_per_pixel as f64;
+        }
+    }
+    (image, counts)
+}
+
+// renders each aov into an image of its own, averaging samples_per_pixel camera
+// rays per pixel like render
+pub fn render_aovs(
+    camera: &Camera,
+    world: &Hittables,
+    aovs: &[Aov],
+    settings: &RenderSettings,
+    rng: &mut rand::rngs::ThreadRng,
+) -> std::vec::Vec<Image> {
+    let width = settings.width;
+    let height = settings.height;
+    let (span_x, span_y) = settings.film_span();
+    let mut layers: std::vec::Vec<Image> = aovs.iter().map(|_| Image::new(width, height)).collect();
+    let mut sampler = settings
+        .sampler
+        .create(settings.samples_per_pixel.max(1) as usize, rng);
+
+    for y in 0..height {
+        let h = height - 1 - y;
+        for x in 0..width {
+            let mut values = vec![Vec3::new(0.0, 0.0, 0.0); aovs.len()];
+            for i in 0..settings.samples_per_pixel {
+                sampler.start_pixel_sample(x, y, i as usize);
+                let (jitter_x, jitter_y) = sampler.get_2d(rng);
+                let u = (x as f64 + jitter_x) / span_x;
+                let v = (h as f64 + jitter_y) / span_y;
+                let lens = sampler.get_2d(rng);
+                let time = sampler.get_1d(rng);
+                let ray = match camera.generate_ray(u, v, lens, time, rng) {
+                    Some(ray) => ray,
+                    None => continue,
+                };
+                for (value, aov) in values.iter_mut().zip(aovs.iter()) {
+                    *value = *value + aov.value(&ray, world, rng);
+                }
+            }
+            for (layer, value) in layers.iter_mut().zip(values.iter()) {
+                layer.set(x, y, *value / settings.samples_per_pixel as f64);
+            }
+        }
+    }
+    layers
+}
+
+// writes the beauty pass to path and each layer next to it, image.ppm putting
+// the normals in image.normal.ppm. layers hold data so they aren't gamma
+// corrected, apart from albedo, and depth is scaled so the furthest hit is white
+pub fn write_layers(path: &str, beauty: &Image, aovs: &[Aov], layers: &[Image]) {
+    beauty.write(path);
+
+    let (stem, extension) = match path.rfind('.') {
+        Some(dot) => (&path[..dot], &path[dot..]),
+        None => (path, ""),
+    };
+    for (aov, layer) in aovs.iter().zip(layers.iter()) {
+        let layer_path = format!("{}.{}{}", stem, aov.name(), extension);
+        match aov {
+            Aov::Albedo => layer.write(&layer_path),
+            Aov::Depth => {
+                let furthest = layer.pixels.iter().fold(0.0, |max: f64, p| max.max(p.x));
+                let mut scaled = Image::new(layer.width, layer.height);
+                if furthest > 0.0 {
+                    for (pixel, p) in scaled.pixels.iter_mut().zip(layer.pixels.iter()) {
+                        *pixel = *p / furthest;
+                    }
+                }
+                scaled.write_linear(&layer_path);
+            }
+            _ => layer.write_linear(&layer_path),
+        }
+    }
+}
+
+// mean pixel of a sphere made of material filling the view, lit by white from
+// every direction. a material that neither gains nor loses energy comes out at
+// 1 however the light bounces around it (white furnace test)
+#[cfg(test)]
+pub(crate) fn white_furnace(
+    material: Box<dyn crate::material::Material>,
+    settings: &RenderSettings,
+) -> f64 {
+    let mut rng = rand::thread_rng();
+    let world = Hittables {
+        list: vec![Box::new(crate::sphere::Sphere::new(
+            Vec3::new(0.0, 0.0, 0.0),
+            1.0,
+            Some(material),
+        ))],
+    };
+    let camera = Camera::new_12_2(
+        Vec3::new(0.0, 0.0, 3.0),
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+        30.0,
+        1.0,
+        0.0,
+        3.0,
+    );
+    let white = crate::background::Constant::new(Vec3::new(1.0, 1.0, 1.0));
+    let image = render(&camera, &world, &white, settings, &mut rng);
+
+    let total: f64 = image.pixels.iter().map(|p| p.x + p.y + p.z).sum();
+    total / (3 * image.pixels.len()) as f64
+}
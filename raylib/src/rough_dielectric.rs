@@ -0,0 +1,143 @@
+use crate::hittable::Hit;
+use crate::material::Material;
+use crate::medium::{Absorbing, Medium};
+use crate::microfacet::{fresnel_dielectric, refract, Ggx};
+use crate::onb::Onb;
+use crate::random;
+use crate::ray::Ray;
+use crate::vec::Vec3;
+
+// frosted glass: ggx microfacets that both reflect and transmit (walter et al. 2007),
+// choosing between the two with the exact fresnel reflectance of each microfacet
+#[derive(Clone)]
+pub struct RoughDielectric {
+    pub index_of_refraction: f64,
+    pub distribution: Ggx,
+    pub absorption: Option<Absorbing>,
+}
+
+impl RoughDielectric {
+    pub fn new(index_of_refraction: f64, roughness: f64) -> RoughDielectric {
+        RoughDielectric {
+            index_of_refraction,
+            distribution: Ggx::new(roughness),
+            absorption: None,
+        }
+    }
+
+    pub fn colored(
+        index_of_refraction: f64,
+        roughness: f64,
+        absorption: Absorbing,
+    ) -> RoughDielectric {
+        RoughDielectric {
+            index_of_refraction,
+            distribution: Ggx::new(roughness),
+            absorption: Some(absorption),
+        }
+    }
+
+    fn eta(&self, hit: &Hit) -> f64 {
+        if hit.front_face {
+            self.index_of_refraction
+        } else {
+            1.0 / self.index_of_refraction
+        }
+    }
+}
+
+impl Material for RoughDielectric {
+    fn scatter(&self, ray: &Ray, hit: Hit, rng: &mut rand::rngs::ThreadRng) -> Option<(Ray, Vec3)> {
+        let attenuation = Vec3::new(1.0, 1.0, 1.0);
+        let eta = self.eta(&hit);
+        let frame = Onb::from_w(hit.normal);
+        let wo = frame.to_local(-ray.direction.unit());
+        if wo.z <= 0.0 {
+            return None;
+        }
+
+        let m = if self.distribution.is_smooth() {
+            Vec3::new(0.0, 0.0, 1.0)
+        } else {
+            self.distribution
+                .sample_visible(wo, random(rng), random(rng))
+        };
+
+        let reflectance = fresnel_dielectric(wo.dot(&m), eta);
+        let wi = if random(rng) < reflectance {
+            let wi = m * (2.0 * wo.dot(&m)) - wo;
+            if wi.z <= 0.0 {
+                return None;
+            }
+            wi
+        } else {
+            match refract(wo, m, eta) {
+                Some(wi) if wi.z < 0.0 => wi,
+                _ => return None,
+            }
+        };
+
+        // fresnel cancels with the choice of lobe, visible normal sampling leaves
+        // only the shadowing of the outgoing direction
+        let weight = if self.distribution.is_smooth() {
+            1.0
+        } else {
+            self.distribution.g(wo, wi) / self.distribution.g1(wo)
+        };
+
+        Some((
+            Ray::new(hit.point, frame.local(wi.x, wi.y, wi.z)),
+            attenuation * weight,
+        ))
+    }
+
+    fn eval(&self, ray: &Ray, hit: &Hit, direction: Vec3) -> Option<(Vec3, f64)> {
+        if self.distribution.is_smooth() {
+            return None;
+        }
+        let none = Some((Vec3::new(0.0, 0.0, 0.0), 0.0));
+
+        let eta = self.eta(hit);
+        let frame = Onb::from_w(hit.normal);
+        let wo = frame.to_local(-ray.direction.unit());
+        let wi = frame.to_local(direction.unit());
+        if wo.z <= 0.0 || wi.z == 0.0 {
+            return none;
+        }
+
+        if wi.z > 0.0 {
+            let m = (wo + wi).unit();
+            let d = self.distribution.d(m);
+            let reflectance = fresnel_dielectric(wo.dot(&m), eta);
+            let f = reflectance * d * self.distribution.g(wo, wi) / (4.0 * wo.z);
+            let pdf = reflectance * self.distribution.g1(wo) * d / (4.0 * wo.z);
+
+            return Some((Vec3::new(f, f, f), pdf));
+        }
+
+        // generalized half vector of the refraction, on the incident side
+        let mut m = (wo + wi * eta).unit();
+        if m.z < 0.0 {
+            m = -m;
+        }
+        let wo_m = wo.dot(&m);
+        let wi_m = wi.dot(&m);
+        if wo_m <= 0.0 || wi_m >= 0.0 {
+            return none;
+        }
+
+        let d = self.distribution.d(m);
+        let transmittance = 1.0 - fresnel_dielectric(wo_m, eta);
+        let denominator = wo_m + eta * wi_m;
+        let jacobian = eta * eta * wi_m.abs() / (denominator * denominator);
+
+        let f = transmittance * d * self.distribution.g(wo, wi) * wo_m * jacobian / wo.z;
+        let pdf = transmittance * self.distribution.g1(wo) * d * wo_m * jacobian / wo.z;
+
+        Some((Vec3::new(f, f, f), pdf))
+    }
+
+    fn interior(&self) -> Option<&dyn Medium> {
+        self.absorption.as_ref().map(|a| a as &dyn Medium)
+    }
+}
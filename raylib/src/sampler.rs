@@ -0,0 +1,530 @@
+use crate::random;
+
+// where the uniform numbers behind each sample come from. a sample takes its
+// dimensions in a fixed order: the position in the pixel, then the point on the
+// lens, the moment in the shutter and the wavelength, then any more asked for.
+// samplers that spread samples well over these converge faster on edges, depth
+// of field and motion blur
+pub trait Sampler {
+    // starts the index-th sample of the pixel, back at the first dimension
+    fn start_pixel_sample(&mut self, x: usize, y: usize, index: usize);
+
+    fn get_1d(&mut self, rng: &mut rand::rngs::ThreadRng) -> f64;
+
+    fn get_2d(&mut self, rng: &mut rand::rngs::ThreadRng) -> (f64, f64);
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum SamplerKind {
+    // every number drawn on its own, what the chapters do
+    Independent,
+    // jittered strata, one per sample, shuffled for every pixel and dimension
+    Stratified,
+    // halton points with owen scrambled digits
+    Halton,
+    // pairs of owen scrambled sobol dimensions (burley, 2020)
+    Sobol,
+    // progressive multi-jittered (0, 2) sequences (christensen et al., 2018)
+    Pmj02,
+}
+
+impl SamplerKind {
+    pub fn create(
+        &self,
+        samples_per_pixel: usize,
+        rng: &mut rand::rngs::ThreadRng,
+    ) -> Box<dyn Sampler> {
+        let samples_per_pixel = samples_per_pixel.max(1);
+        let pixel = PixelSample::new(rng);
+        match self {
+            SamplerKind::Independent => Box::new(Independent {}),
+            SamplerKind::Stratified => Box::new(Stratified {
+                pixel,
+                samples_per_pixel,
+            }),
+            SamplerKind::Halton => Box::new(Halton { pixel }),
+            SamplerKind::Sobol => Box::new(Sobol { pixel }),
+            SamplerKind::Pmj02 => {
+                let count = samples_per_pixel.next_power_of_two().min(PMJ02_MAX_POINTS);
+                Box::new(Pmj02 {
+                    pixel,
+                    samples_per_pixel,
+                    sets: (0..PMJ02_SETS).map(|_| pmj02(count, rng)).collect(),
+                })
+            }
+        }
+    }
+}
+
+// splitmix64's finalizer
+fn mix(h: u64) -> u64 {
+    let mut h = h;
+    h ^= h >> 30;
+    h = h.wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    h ^= h >> 27;
+    h = h.wrapping_mul(0x94d0_49bb_1331_11eb);
+    h ^ (h >> 31)
+}
+
+fn hash(values: &[u64]) -> u64 {
+    values
+        .iter()
+        .fold(0x9e37_79b9_7f4a_7c15, |h, v| mix(h ^ mix(*v)))
+}
+
+fn bits_to_unit(bits: u32) -> f64 {
+    bits as f64 / 4_294_967_296.0
+}
+
+// the i-th element of a random permutation of 0..l picked by p, without
+// building it (kensler, 2013)
+fn permutation_element(i: u32, l: u32, p: u32) -> u32 {
+    let mut w = l - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+
+    let mut i = i;
+    loop {
+        i ^= p;
+        i = i.wrapping_mul(0xe170_893d);
+        i ^= p >> 16;
+        i ^= (i & w) >> 4;
+        i ^= p >> 8;
+        i = i.wrapping_mul(0x0929_eb3f);
+        i ^= p >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | p >> 27);
+        i = i.wrapping_mul(0x6935_fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dc_b303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e50_1cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860_a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < l {
+            return (i.wrapping_add(p)) % l;
+        }
+    }
+}
+
+// owen scrambling of the bits of x, most significant first, as a hash of the
+// bits above each one (laine and karras, 2011)
+fn owen_scramble(x: u32, seed: u32) -> u32 {
+    let mut x = x.reverse_bits();
+    x = x.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50_b47c);
+    x ^= x.wrapping_mul(0xb82f_1e52);
+    x ^= x.wrapping_mul(0xc7af_e638);
+    x ^= x.wrapping_mul(0x8d22_f6e6);
+    x.reverse_bits()
+}
+
+// which pixel, sample and dimension the sampler is at, and the render's seed
+// that scrambles differently every render
+struct PixelSample {
+    seed: u64,
+    x: u64,
+    y: u64,
+    index: u64,
+    dimension: u64,
+}
+
+impl PixelSample {
+    fn new(rng: &mut rand::rngs::ThreadRng) -> PixelSample {
+        PixelSample {
+            seed: (random(rng) * u32::MAX as f64) as u64,
+            x: 0,
+            y: 0,
+            index: 0,
+            dimension: 0,
+        }
+    }
+
+    fn start(&mut self, x: usize, y: usize, index: usize) {
+        self.x = x as u64;
+        self.y = y as u64;
+        self.index = index as u64;
+        self.dimension = 0;
+    }
+
+    // hash of the pixel and the next dimensions, which it moves past
+    fn next(&mut self, dimensions: u64) -> u64 {
+        let h = hash(&[self.x, self.y, self.dimension, self.seed]);
+        self.dimension += dimensions;
+        h
+    }
+}
+
+pub struct Independent {}
+
+impl Sampler for Independent {
+    fn start_pixel_sample(&mut self, _x: usize, _y: usize, _index: usize) {}
+
+    fn get_1d(&mut self, rng: &mut rand::rngs::ThreadRng) -> f64 {
+        random(rng)
+    }
+
+    fn get_2d(&mut self, rng: &mut rand::rngs::ThreadRng) -> (f64, f64) {
+        (random(rng), random(rng))
+    }
+}
+
+pub struct Stratified {
+    pixel: PixelSample,
+    samples_per_pixel: usize,
+}
+
+impl Sampler for Stratified {
+    fn start_pixel_sample(&mut self, x: usize, y: usize, index: usize) {
+        self.pixel.start(x, y, index);
+    }
+
+    fn get_1d(&mut self, rng: &mut rand::rngs::ThreadRng) -> f64 {
+        let n = self.samples_per_pixel as u32;
+        let index = (self.pixel.index % n as u64) as u32;
+        let stratum = permutation_element(index, n, self.pixel.next(1) as u32);
+        (stratum as f64 + random(rng)) / n as f64
+    }
+
+    // a grid at least as big as the sample count, so a few strata stay empty
+    // when it isn't square
+    fn get_2d(&mut self, rng: &mut rand::rngs::ThreadRng) -> (f64, f64) {
+        let n = self.samples_per_pixel;
+        let nx = (n as f64).sqrt().ceil() as usize;
+        let ny = n.div_ceil(nx);
+        let index = (self.pixel.index % n as u64) as u32;
+        let stratum = permutation_element(index, (nx * ny) as u32, self.pixel.next(2) as u32);
+        let (sx, sy) = (stratum as usize % nx, stratum as usize / nx);
+        (
+            (sx as f64 + random(rng)) / nx as f64,
+            (sy as f64 + random(rng)) / ny as f64,
+        )
+    }
+}
+
+const PRIMES: [u64; 64] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+    101, 103, 107, 109, 113, 127, 131, 137, 139, 149, 151, 157, 163, 167, 173, 179, 181, 191, 193,
+    197, 199, 211, 223, 227, 229, 233, 239, 241, 251, 257, 263, 269, 271, 277, 281, 283, 293, 307,
+    311,
+];
+
+// the index's digits in base mirrored around the point, each shifted by a hash
+// of the digits before it, which keeps the points' stratification
+fn scrambled_radical_inverse(base: u64, index: u64, seed: u64) -> f64 {
+    let inverse_base = 1.0 / base as f64;
+    let mut inverse_base_m = 1.0;
+    let mut reversed = 0u64;
+    let mut index = index;
+    let mut position = 0u64;
+    // until the digits are past what a double holds
+    while 1.0 - (base - 1) as f64 * inverse_base_m < 1.0 {
+        let next = index / base;
+        let digit = index - next * base;
+        let shift = hash(&[seed, position, reversed]) % base;
+        position += 1;
+        reversed = reversed * base + (digit + shift) % base;
+        inverse_base_m *= inverse_base;
+        index = next;
+    }
+    (reversed as f64 * inverse_base_m).min(1.0 - f64::EPSILON / 2.0)
+}
+
+pub struct Halton {
+    pixel: PixelSample,
+}
+
+impl Sampler for Halton {
+    fn start_pixel_sample(&mut self, x: usize, y: usize, index: usize) {
+        self.pixel.start(x, y, index);
+    }
+
+    // dimensions past the table of primes are drawn independently
+    fn get_1d(&mut self, rng: &mut rand::rngs::ThreadRng) -> f64 {
+        let dimension = self.pixel.dimension as usize;
+        let seed = self.pixel.next(1);
+        match PRIMES.get(dimension) {
+            Some(base) => scrambled_radical_inverse(*base, self.pixel.index, seed),
+            None => random(rng),
+        }
+    }
+
+    fn get_2d(&mut self, rng: &mut rand::rngs::ThreadRng) -> (f64, f64) {
+        let x = self.get_1d(rng);
+        (x, self.get_1d(rng))
+    }
+}
+
+// second sobol dimension, from the primitive polynomial x + 1
+fn sobol_second(index: u32) -> u32 {
+    let mut result = 0;
+    let mut direction = 1u32 << 31;
+    let mut index = index;
+    while index != 0 {
+        if index & 1 != 0 {
+            result ^= direction;
+        }
+        index >>= 1;
+        direction ^= direction >> 1;
+    }
+    result
+}
+
+// every pair of dimensions is the first two sobol dimensions, a (0, 2)
+// sequence, with the index shuffled and the values scrambled independently
+pub struct Sobol {
+    pixel: PixelSample,
+}
+
+impl Sampler for Sobol {
+    fn start_pixel_sample(&mut self, x: usize, y: usize, index: usize) {
+        self.pixel.start(x, y, index);
+    }
+
+    fn get_1d(&mut self, _rng: &mut rand::rngs::ThreadRng) -> f64 {
+        let h = self.pixel.next(1);
+        let index = owen_scramble(self.pixel.index as u32, h as u32);
+        bits_to_unit(owen_scramble(index.reverse_bits(), (h >> 32) as u32))
+    }
+
+    fn get_2d(&mut self, _rng: &mut rand::rngs::ThreadRng) -> (f64, f64) {
+        let h = self.pixel.next(2);
+        let index = owen_scramble(self.pixel.index as u32, h as u32);
+        let h2 = mix(h);
+        (
+            bits_to_unit(owen_scramble(index.reverse_bits(), h2 as u32)),
+            bits_to_unit(owen_scramble(sobol_second(index), (h2 >> 32) as u32)),
+        )
+    }
+}
+
+// tables of points generated per render, which pixels and dimensions pick from
+const PMJ02_SETS: usize = 4;
+const PMJ02_MAX_POINTS: usize = 4096;
+
+// a progressive multi-jittered (0, 2) sequence of count points, a power of two:
+// every power of two prefix has one point in each elementary interval of its
+// size. the points fill in the opposite subquadrant of every cell, then the two
+// remaining ones, each in strata no earlier point holds
+fn pmj02(count: usize, rng: &mut rand::rngs::ThreadRng) -> std::vec::Vec<(f64, f64)> {
+    let mut points = vec![(random(rng), random(rng))];
+    while points.len() < count {
+        // on the rare dead end where no strata are left, the step starts over
+        loop {
+            let mut extended = points.clone();
+            if extend_pmj02(&mut extended, rng) {
+                points = extended;
+                break;
+            }
+        }
+    }
+    points
+}
+
+fn extend_pmj02(points: &mut std::vec::Vec<(f64, f64)>, rng: &mut rand::rngs::ThreadRng) -> bool {
+    let n = points.len();
+    let total = 2 * n;
+    let levels = total.trailing_zeros() as usize;
+
+    // occupied[a] marks the intervals 2^a wide by 2^(levels - a) tall
+    let mut occupied = vec![vec![false; total]; levels + 1];
+    let strata =
+        |x: usize, y: usize, a: usize| (x >> (levels - a)) * (1 << (levels - a)) + (y >> a);
+    let finest = |p: (f64, f64)| {
+        (
+            ((p.0 * total as f64) as usize).min(total - 1),
+            ((p.1 * total as f64) as usize).min(total - 1),
+        )
+    };
+    for p in points.iter() {
+        let (x, y) = finest(*p);
+        for (a, shape) in occupied.iter_mut().enumerate() {
+            shape[strata(x, y, a)] = true;
+        }
+    }
+
+    // subquadrants the new points go to, as (cell x, cell y, half x, half y) in
+    // a grid of cells
+    let square = n.trailing_zeros().is_multiple_of(2);
+    let grid = if square {
+        (n as f64).sqrt().round() as usize
+    } else {
+        ((n / 2) as f64).sqrt().round() as usize
+    };
+    let subquadrant = |p: (f64, f64)| {
+        let (gx, gy) = (p.0 * grid as f64, p.1 * grid as f64);
+        let (cx, cy) = ((gx as usize).min(grid - 1), (gy as usize).min(grid - 1));
+        let half = |g: f64, c: usize| (((g - c as f64) * 2.0) as usize).min(1);
+        (cx, cy, half(gx, cx), half(gy, cy))
+    };
+    let mut targets = std::vec::Vec::with_capacity(n);
+    if square {
+        for p in points.iter() {
+            let (cx, cy, hx, hy) = subquadrant(*p);
+            targets.push((cx, cy, 1 - hx, 1 - hy));
+        }
+    } else {
+        let mut second = std::vec::Vec::with_capacity(n / 2);
+        for p in points[..n / 2].iter() {
+            let (cx, cy, hx, hy) = subquadrant(*p);
+            let (a, b) = ((cx, cy, 1 - hx, hy), (cx, cy, hx, 1 - hy));
+            if random(rng) < 0.5 {
+                targets.push(a);
+                second.push(b);
+            } else {
+                targets.push(b);
+                second.push(a);
+            }
+        }
+        targets.extend(second);
+    }
+
+    // finest strata across a subquadrant
+    let span = total / (2 * grid);
+    let mut candidates = std::vec::Vec::with_capacity(span * span);
+    for (cx, cy, hx, hy) in targets {
+        let x0 = (2 * cx + hx) * span;
+        let y0 = (2 * cy + hy) * span;
+        candidates.clear();
+        for x in x0..x0 + span {
+            for y in y0..y0 + span {
+                candidates.push((x, y));
+            }
+        }
+        // shuffled so the first free one is a random one
+        for i in (1..candidates.len()).rev() {
+            let j = ((random(rng) * (i + 1) as f64) as usize).min(i);
+            candidates.swap(i, j);
+        }
+
+        let free = candidates.iter().find(|(x, y)| {
+            occupied
+                .iter()
+                .enumerate()
+                .all(|(a, shape)| !shape[strata(*x, *y, a)])
+        });
+        let (x, y) = match free {
+            Some(free) => *free,
+            None => return false,
+        };
+        for (a, shape) in occupied.iter_mut().enumerate() {
+            shape[strata(x, y, a)] = true;
+        }
+        points.push((
+            (x as f64 + random(rng)) / total as f64,
+            (y as f64 + random(rng)) / total as f64,
+        ));
+    }
+    true
+}
+
+pub struct Pmj02 {
+    pixel: PixelSample,
+    samples_per_pixel: usize,
+    sets: std::vec::Vec<std::vec::Vec<(f64, f64)>>,
+}
+
+impl Sampler for Pmj02 {
+    fn start_pixel_sample(&mut self, x: usize, y: usize, index: usize) {
+        self.pixel.start(x, y, index);
+    }
+
+    // shuffled jittered strata, like the stratified sampler
+    fn get_1d(&mut self, rng: &mut rand::rngs::ThreadRng) -> f64 {
+        let n = self.samples_per_pixel as u32;
+        let index = (self.pixel.index % n as u64) as u32;
+        let stratum = permutation_element(index, n, self.pixel.next(1) as u32);
+        (stratum as f64 + random(rng)) / n as f64
+    }
+
+    // a point from one of the sets, its bits flipped by the pixel and dimension
+    // which moves it to another point of the same elementary intervals' kind
+    fn get_2d(&mut self, _rng: &mut rand::rngs::ThreadRng) -> (f64, f64) {
+        let h = self.pixel.next(2);
+        let count = self.sets[0].len() as u64;
+        let round = self.pixel.index / count;
+        let set = (mix(h ^ round) % PMJ02_SETS as u64) as usize;
+        let (x, y) = self.sets[set][(self.pixel.index % count) as usize];
+
+        let flip = mix(h.wrapping_add(round));
+        let scramble = |u: f64, bits: u32| bits_to_unit((u * 4_294_967_296.0) as u32 ^ bits);
+        (scramble(x, flip as u32), scramble(y, (flip >> 32) as u32))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // every elementary interval of the first n points' finest shapes, 2^a wide
+    // by n / 2^a tall, holds exactly one of them
+    fn stratified(points: &[(f64, f64)]) -> bool {
+        let n = points.len();
+        let levels = n.trailing_zeros();
+        (0..=levels).all(|a| {
+            let (columns, rows) = (1 << a, n >> a);
+            let mut occupied = vec![false; n];
+            points.iter().all(|(x, y)| {
+                let cell = ((x * columns as f64) as usize).min(columns - 1) * rows
+                    + ((y * rows as f64) as usize).min(rows - 1);
+                !std::mem::replace(&mut occupied[cell], true)
+            })
+        })
+    }
+
+    fn first_points(kind: SamplerKind, n: usize, dimensions: usize) -> bool {
+        let mut rng = rand::thread_rng();
+        let mut sampler = kind.create(n, &mut rng);
+        let mut points = vec![std::vec::Vec::new(); dimensions];
+        for i in 0..n {
+            sampler.start_pixel_sample(3, 5, i);
+            for dimension in points.iter_mut() {
+                dimension.push(sampler.get_2d(&mut rng));
+            }
+        }
+        points.iter().all(|points| stratified(points))
+    }
+
+    #[test]
+    fn sobol_points_land_one_per_stratum() {
+        for n in [1, 2, 4, 16, 64, 256] {
+            assert!(first_points(SamplerKind::Sobol, n, 4));
+        }
+    }
+
+    #[test]
+    fn pmj02_points_land_one_per_stratum() {
+        for n in [1, 2, 4, 16, 64, 256] {
+            assert!(first_points(SamplerKind::Pmj02, n, 4));
+        }
+    }
+
+    #[test]
+    fn pmj02_prefixes_stay_stratified() {
+        let mut rng = rand::thread_rng();
+        let points = pmj02(128, &mut rng);
+        for n in [1, 2, 4, 8, 16, 32, 64, 128] {
+            assert!(stratified(&points[..n]));
+        }
+    }
+
+    #[test]
+    fn stratified_1d_takes_each_stratum_once() {
+        let mut rng = rand::thread_rng();
+        let n = 10;
+        let mut sampler = SamplerKind::Stratified.create(n, &mut rng);
+        let mut strata: std::vec::Vec<usize> = (0..n)
+            .map(|i| {
+                sampler.start_pixel_sample(0, 0, i);
+                (sampler.get_1d(&mut rng) * n as f64) as usize
+            })
+            .collect();
+        strata.sort_unstable();
+        assert_eq!(strata, (0..n).collect::<std::vec::Vec<usize>>());
+    }
+}
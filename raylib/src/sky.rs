@@ -0,0 +1,248 @@
+use crate::background::Background;
+use crate::onb::Onb;
+use crate::vec::Vec3;
+use crate::{uniform_sphere, xyz_to_rgb};
+
+use std::f64::consts::PI;
+
+// angular radius of the sun disk in radians
+pub const SUN_ANGULAR_RADIUS: f64 = 0.00465;
+
+// luminance of the sun outside the atmosphere, in cd/m²
+pub const SUN_LUMINANCE: f64 = 1.6e9;
+
+// default scale from cd/m² to the radiance the renderer works with, this keeps a
+// white lambertian surface under a high sun a little below 1
+pub const DEFAULT_INTENSITY: f64 = 2.0e-5;
+
+// perez luminance distribution F(theta, gamma) with coefficients A..E
+fn perez(c: &[f64; 5], cos_theta: f64, gamma: f64) -> f64 {
+    (1.0 + c[0] * (c[1] / cos_theta).exp())
+        * (1.0 + c[2] * (c[3] * gamma).exp() + c[4] * gamma.cos() * gamma.cos())
+}
+
+// zenith angle of the sun, clamped to the horizon where the model stops being valid
+fn sun_zenith(sun_direction: Vec3) -> f64 {
+    sun_direction.unit().y.clamp(0.0, 1.0).acos()
+}
+
+// analytic daylight model of preetham, shirley and smits (1999), y is up
+pub struct PreethamSky {
+    pub sun_direction: Vec3,
+    pub turbidity: f64,
+    pub intensity: f64,
+    // perez coefficients for luminance Y and chromaticities x and y
+    pub coefficients: [[f64; 5]; 3],
+    // Y, x and y straight up, Y in cd/m²
+    pub zenith: Vec3,
+}
+
+impl PreethamSky {
+    pub fn new(sun_direction: Vec3, turbidity: f64, intensity: f64) -> PreethamSky {
+        let t = turbidity;
+        let theta_s = sun_zenith(sun_direction);
+
+        let coefficients = [
+            [
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ],
+            [
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ],
+            [
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ],
+        ];
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_s);
+        let zenith_luminance = ((4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192) * 1000.0;
+
+        let th = theta_s;
+        let th2 = th * th;
+        let th3 = th2 * th;
+        let zenith_x = t * t * (0.00166 * th3 - 0.00375 * th2 + 0.00209 * th)
+            + t * (-0.02903 * th3 + 0.06377 * th2 - 0.03202 * th + 0.00394)
+            + (0.11693 * th3 - 0.21196 * th2 + 0.06052 * th + 0.25886);
+        let zenith_y = t * t * (0.00275 * th3 - 0.00610 * th2 + 0.00317 * th)
+            + t * (-0.04214 * th3 + 0.08970 * th2 - 0.04153 * th + 0.00516)
+            + (0.15346 * th3 - 0.26756 * th2 + 0.06670 * th + 0.26688);
+
+        PreethamSky {
+            sun_direction: sun_direction.unit(),
+            turbidity,
+            intensity,
+            coefficients,
+            zenith: Vec3::new(zenith_luminance.max(0.0), zenith_x, zenith_y),
+        }
+    }
+}
+
+impl Background for PreethamSky {
+    fn color(&self, direction: Vec3) -> Vec3 {
+        let d = direction.unit();
+        let theta_s = sun_zenith(self.sun_direction);
+
+        // below the horizon repeats the horizon
+        let cos_theta = d.y.max(0.001);
+        let gamma = d.dot(&self.sun_direction).clamp(-1.0, 1.0).acos();
+
+        let mut xy_y = [0.0; 3];
+        let zenith = [self.zenith.x, self.zenith.y, self.zenith.z];
+        for (i, c) in self.coefficients.iter().enumerate() {
+            xy_y[i] = zenith[i] * perez(c, cos_theta, gamma) / perez(c, 1.0, theta_s);
+        }
+        let (luminance, x, y) = (xy_y[0], xy_y[1], xy_y[2]);
+        if y <= 0.0 {
+            return Vec3::new(0.0, 0.0, 0.0);
+        }
+
+        let xyz = Vec3::new(x / y * luminance, luminance, (1.0 - x - y) / y * luminance);
+        let rgb = xyz_to_rgb(xyz) * self.intensity;
+
+        Vec3::new(rgb.x.max(0.0), rgb.y.max(0.0), rgb.z.max(0.0))
+    }
+}
+
+// the sun as a small disk of constant radiance, attenuated by rayleigh and aerosol
+// scattering along its path through the atmosphere
+pub struct Sun {
+    pub direction: Vec3,
+    pub radiance: Vec3,
+    pub angular_radius: f64,
+}
+
+impl Sun {
+    pub fn new(direction: Vec3, turbidity: f64, intensity: f64) -> Sun {
+        let direction = direction.unit();
+        if direction.y <= 0.0 {
+            return Sun {
+                direction,
+                radiance: Vec3::new(0.0, 0.0, 0.0),
+                angular_radius: SUN_ANGULAR_RADIUS,
+            };
+        }
+
+        // relative optical mass (kasten 1966)
+        let theta_s = sun_zenith(direction);
+        let degrees = theta_s.to_degrees();
+        let m = 1.0 / (theta_s.cos() + 0.15 * (93.885 - degrees).powf(-1.253));
+
+        // angstrom turbidity from the linke turbidity used by the sky
+        let beta = 0.04608 * turbidity - 0.04586;
+        let transmittance = |lambda_um: f64| {
+            let rayleigh = (-0.008735 * m * lambda_um.powf(-4.08)).exp();
+            let aerosol = (-beta * m * lambda_um.powf(-1.3)).exp();
+            rayleigh * aerosol
+        };
+
+        Sun {
+            direction,
+            radiance: Vec3::new(
+                transmittance(0.68),
+                transmittance(0.55),
+                transmittance(0.44),
+            ) * (SUN_LUMINANCE * intensity),
+            angular_radius: SUN_ANGULAR_RADIUS,
+        }
+    }
+
+    fn cos_max(&self) -> f64 {
+        self.angular_radius.cos()
+    }
+}
+
+impl Background for Sun {
+    fn color(&self, direction: Vec3) -> Vec3 {
+        if direction.unit().dot(&self.direction) >= self.cos_max() {
+            return self.radiance;
+        }
+        Vec3::new(0.0, 0.0, 0.0)
+    }
+
+    // uniform over the cone the disk subtends
+    fn sample(&self, u: (f64, f64)) -> Option<(Vec3, Vec3, f64)> {
+        let cos_max = self.cos_max();
+        let cos_theta = 1.0 - u.0 * (1.0 - cos_max);
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * u.1;
+
+        let direction = Onb::from_w(self.direction).local(
+            sin_theta * phi.cos(),
+            sin_theta * phi.sin(),
+            cos_theta,
+        );
+
+        Some((direction, self.radiance, 1.0 / (2.0 * PI * (1.0 - cos_max))))
+    }
+
+    fn pdf(&self, direction: Vec3) -> f64 {
+        let cos_max = self.cos_max();
+        if direction.unit().dot(&self.direction) >= cos_max {
+            return 1.0 / (2.0 * PI * (1.0 - cos_max));
+        }
+        0.0
+    }
+}
+
+// preetham sky together with the sun that lights it, for outdoor scenes
+pub struct SunSky {
+    pub sky: PreethamSky,
+    pub sun: Sun,
+    // chance of sampling the sun rather than the sky
+    pub sun_probability: f64,
+}
+
+impl SunSky {
+    pub fn new(sun_direction: Vec3, turbidity: f64) -> SunSky {
+        SunSky::with_intensity(sun_direction, turbidity, DEFAULT_INTENSITY)
+    }
+
+    pub fn with_intensity(sun_direction: Vec3, turbidity: f64, intensity: f64) -> SunSky {
+        let sun = Sun::new(sun_direction, turbidity, intensity);
+        let sun_probability = if sun.radiance.near_zero() { 0.0 } else { 0.5 };
+
+        SunSky {
+            sky: PreethamSky::new(sun_direction, turbidity, intensity),
+            sun,
+            sun_probability,
+        }
+    }
+}
+
+impl Background for SunSky {
+    fn color(&self, direction: Vec3) -> Vec3 {
+        self.sky.color(direction) + self.sun.color(direction)
+    }
+
+    // the first number picks the sun or the sky and is stretched back over the
+    // unit interval for the one picked
+    fn sample(&self, u: (f64, f64)) -> Option<(Vec3, Vec3, f64)> {
+        let p = self.sun_probability;
+        let direction = if u.0 < p {
+            match self.sun.sample((u.0 / p, u.1)) {
+                Some((direction, _, _)) => direction,
+                None => return None,
+            }
+        } else {
+            uniform_sphere((u.0 - p) / (1.0 - p), u.1)
+        };
+
+        Some((direction, self.color(direction), self.pdf(direction)))
+    }
+
+    fn pdf(&self, direction: Vec3) -> f64 {
+        self.sun_probability * self.sun.pdf(direction) + (1.0 - self.sun_probability) / (4.0 * PI)
+    }
+}
@@ -0,0 +1,381 @@
+use crate::vec::Vec3;
+
+use std::ops;
+
+pub const LAMBDA_MIN: f64 = 360.0;
+pub const LAMBDA_MAX: f64 = 830.0;
+
+// wavelengths carried by each path: a hero wavelength and evenly spaced companions
+pub const N_WAVELENGTHS: usize = 4;
+
+// integral of the y color matching function below, over [LAMBDA_MIN, LAMBDA_MAX]
+const CIE_Y_INTEGRAL: f64 = 106.922;
+
+// cie 1931 color matching functions, multi-lobe gaussian fit of wyman, sloan and
+// shirley (2013)
+fn lobe(lambda: f64, mu: f64, sigma_below: f64, sigma_above: f64) -> f64 {
+    let sigma = if lambda < mu {
+        sigma_below
+    } else {
+        sigma_above
+    };
+    let t = (lambda - mu) / sigma;
+
+    (-0.5 * t * t).exp()
+}
+
+pub fn cie_x(lambda: f64) -> f64 {
+    1.056 * lobe(lambda, 599.8, 37.9, 31.0) + 0.362 * lobe(lambda, 442.0, 16.0, 26.7)
+        - 0.065 * lobe(lambda, 501.1, 20.4, 26.2)
+}
+
+pub fn cie_y(lambda: f64) -> f64 {
+    0.821 * lobe(lambda, 568.8, 46.9, 40.5) + 0.286 * lobe(lambda, 530.9, 16.3, 31.1)
+}
+
+pub fn cie_z(lambda: f64) -> f64 {
+    1.217 * lobe(lambda, 437.0, 11.8, 36.0) + 0.681 * lobe(lambda, 459.0, 26.0, 13.8)
+}
+
+// cie standard illuminant d65 from 360nm to 830nm in 10nm steps
+const D65: [f64; 48] = [
+    46.6383, 52.0891, 49.9755, 54.6482, 82.7549, 91.486, 93.4318, 86.6823, 104.865, 117.008,
+    117.812, 114.861, 115.923, 108.811, 109.354, 107.802, 104.79, 107.689, 104.405, 104.046, 100.0,
+    96.3342, 95.788, 88.6856, 90.0062, 89.5991, 87.6987, 83.2886, 83.6992, 80.0268, 80.2146,
+    82.2778, 78.2842, 69.7213, 71.6091, 74.349, 61.604, 69.8856, 75.087, 63.5927, 46.4182, 66.8054,
+    63.3828, 64.304, 59.4519, 51.959, 57.4406, 60.3125,
+];
+
+// scales D65 to a luminance of 1
+const D65_NORMALIZATION: f64 = 0.0101162;
+
+pub fn d65(lambda: f64) -> f64 {
+    let t = ((lambda - LAMBDA_MIN) / 10.0).clamp(0.0, (D65.len() - 1) as f64);
+    let i = (t as usize).min(D65.len() - 2);
+    let f = t - i as f64;
+
+    (D65[i] * (1.0 - f) + D65[i + 1] * f) * D65_NORMALIZATION
+}
+
+// rgb to reflectance spectrum conversion of smits (1999), 10 bins over 380nm - 720nm
+const SMITS_WHITE: [f64; 10] = [
+    1.0000, 1.0000, 0.9999, 0.9993, 0.9992, 0.9998, 1.0000, 1.0000, 1.0000, 1.0000,
+];
+const SMITS_CYAN: [f64; 10] = [
+    0.9710, 0.9426, 1.0007, 1.0007, 1.0007, 1.0007, 0.1564, 0.0000, 0.0000, 0.0000,
+];
+const SMITS_MAGENTA: [f64; 10] = [
+    1.0000, 1.0000, 0.9685, 0.2229, 0.0000, 0.0458, 0.8369, 1.0000, 1.0000, 0.9959,
+];
+const SMITS_YELLOW: [f64; 10] = [
+    0.0001, 0.0000, 0.1088, 0.6651, 1.0000, 1.0000, 0.9996, 0.9586, 0.9685, 0.9840,
+];
+const SMITS_RED: [f64; 10] = [
+    0.1012, 0.0515, 0.0000, 0.0000, 0.0000, 0.0000, 0.8325, 1.0149, 1.0149, 1.0149,
+];
+const SMITS_GREEN: [f64; 10] = [
+    0.0000, 0.0000, 0.0273, 0.7937, 1.0000, 0.9418, 0.1719, 0.0000, 0.0000, 0.0025,
+];
+const SMITS_BLUE: [f64; 10] = [
+    1.0000, 1.0000, 0.8916, 0.3323, 0.0000, 0.0000, 0.0003, 0.0369, 0.0483, 0.0496,
+];
+
+// linear interpolation between the bin centers
+fn smits(table: &[f64; 10], lambda: f64) -> f64 {
+    let width = (720.0 - 380.0) / 10.0;
+    let t = (lambda - 380.0) / width - 0.5;
+    if t <= 0.0 {
+        return table[0];
+    }
+    if t >= 9.0 {
+        return table[9];
+    }
+    let i = t as usize;
+    let f = t - i as f64;
+
+    table[i] * (1.0 - f) + table[i + 1] * f
+}
+
+// value at `lambda` of a smooth reflectance spectrum with the given rgb color
+pub fn rgb_to_reflectance(rgb: Vec3, lambda: f64) -> f64 {
+    let (r, g, b) = (rgb.x, rgb.y, rgb.z);
+    let s = |table| smits(table, lambda);
+
+    if r <= g && r <= b {
+        if g <= b {
+            r * s(&SMITS_WHITE) + (g - r) * s(&SMITS_CYAN) + (b - g) * s(&SMITS_BLUE)
+        } else {
+            r * s(&SMITS_WHITE) + (b - r) * s(&SMITS_CYAN) + (g - b) * s(&SMITS_GREEN)
+        }
+    } else if g <= r && g <= b {
+        if r <= b {
+            g * s(&SMITS_WHITE) + (r - g) * s(&SMITS_MAGENTA) + (b - r) * s(&SMITS_BLUE)
+        } else {
+            g * s(&SMITS_WHITE) + (b - g) * s(&SMITS_MAGENTA) + (r - b) * s(&SMITS_RED)
+        }
+    } else if r <= g {
+        b * s(&SMITS_WHITE) + (r - b) * s(&SMITS_YELLOW) + (g - r) * s(&SMITS_GREEN)
+    } else {
+        b * s(&SMITS_WHITE) + (g - b) * s(&SMITS_YELLOW) + (r - g) * s(&SMITS_RED)
+    }
+}
+
+// emitted light is upsampled relative to d65 so that rgb white stays white
+pub fn rgb_to_illuminant(rgb: Vec3, lambda: f64) -> f64 {
+    rgb_to_reflectance(rgb, lambda) * d65(lambda)
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Wavelengths {
+    pub lambda: [f64; N_WAVELENGTHS],
+    pub pdf: [f64; N_WAVELENGTHS],
+}
+
+impl Wavelengths {
+    // hero wavelength sampling (wilkie et al. 2014): u picks the hero uniformly and
+    // the others are rotated evenly around the visible range
+    pub fn sample(u: f64) -> Wavelengths {
+        let range = LAMBDA_MAX - LAMBDA_MIN;
+        let hero = LAMBDA_MIN + u * range;
+
+        let mut lambda = [0.0; N_WAVELENGTHS];
+        for (i, l) in lambda.iter_mut().enumerate() {
+            let offset = range * i as f64 / N_WAVELENGTHS as f64;
+            *l = LAMBDA_MIN + (hero - LAMBDA_MIN + offset) % range;
+        }
+
+        Wavelengths {
+            lambda,
+            pdf: [1.0 / range; N_WAVELENGTHS],
+        }
+    }
+
+    pub fn hero(&self) -> f64 {
+        self.lambda[0]
+    }
+
+    pub fn secondary_terminated(&self) -> bool {
+        self.pdf[1..].iter().all(|pdf| *pdf == 0.0)
+    }
+
+    // after a wavelength dependent scattering (dispersion) only the hero can follow
+    // the path, the others are dropped and the hero carries all the weight
+    pub fn terminate_secondary(&mut self) {
+        if self.secondary_terminated() {
+            return;
+        }
+        for pdf in self.pdf[1..].iter_mut() {
+            *pdf = 0.0;
+        }
+        self.pdf[0] /= N_WAVELENGTHS as f64;
+    }
+}
+
+// a spectrum sampled at the wavelengths of a path
+#[derive(Copy, Clone, Debug)]
+pub struct SampledSpectrum {
+    pub values: [f64; N_WAVELENGTHS],
+}
+
+impl ops::Add<SampledSpectrum> for SampledSpectrum {
+    type Output = SampledSpectrum;
+
+    fn add(self, rhs: SampledSpectrum) -> SampledSpectrum {
+        let mut values = self.values;
+        for (v, r) in values.iter_mut().zip(rhs.values.iter()) {
+            *v += r;
+        }
+        SampledSpectrum { values }
+    }
+}
+
+impl ops::Mul<SampledSpectrum> for SampledSpectrum {
+    type Output = SampledSpectrum;
+
+    fn mul(self, rhs: SampledSpectrum) -> SampledSpectrum {
+        let mut values = self.values;
+        for (v, r) in values.iter_mut().zip(rhs.values.iter()) {
+            *v *= r;
+        }
+        SampledSpectrum { values }
+    }
+}
+
+impl ops::Sub<SampledSpectrum> for SampledSpectrum {
+    type Output = SampledSpectrum;
+
+    fn sub(self, rhs: SampledSpectrum) -> SampledSpectrum {
+        let mut values = self.values;
+        for (v, r) in values.iter_mut().zip(rhs.values.iter()) {
+            *v -= r;
+        }
+        SampledSpectrum { values }
+    }
+}
+
+impl ops::Mul<f64> for SampledSpectrum {
+    type Output = SampledSpectrum;
+
+    fn mul(self, rhs: f64) -> SampledSpectrum {
+        let mut values = self.values;
+        for v in values.iter_mut() {
+            *v *= rhs;
+        }
+        SampledSpectrum { values }
+    }
+}
+
+impl SampledSpectrum {
+    pub fn new(value: f64) -> SampledSpectrum {
+        SampledSpectrum {
+            values: [value; N_WAVELENGTHS],
+        }
+    }
+
+    pub fn from_fn<F: Fn(f64) -> f64>(wavelengths: &Wavelengths, f: F) -> SampledSpectrum {
+        let mut values = [0.0; N_WAVELENGTHS];
+        for (v, lambda) in values.iter_mut().zip(wavelengths.lambda.iter()) {
+            *v = f(*lambda);
+        }
+        SampledSpectrum { values }
+    }
+
+    // for reflectances and other unitless colors
+    pub fn from_rgb(rgb: Vec3, wavelengths: &Wavelengths) -> SampledSpectrum {
+        SampledSpectrum::from_fn(wavelengths, |lambda| rgb_to_reflectance(rgb, lambda))
+    }
+
+    // for emitted radiance
+    pub fn from_illuminant(rgb: Vec3, wavelengths: &Wavelengths) -> SampledSpectrum {
+        SampledSpectrum::from_fn(wavelengths, |lambda| rgb_to_illuminant(rgb, lambda))
+    }
+
+    pub fn is_black(&self) -> bool {
+        self.values.iter().all(|v| *v == 0.0)
+    }
+
+    // monte carlo estimate of the cie xyz color of the spectrum, Y of 1 for d65
+    pub fn to_xyz(&self, wavelengths: &Wavelengths) -> Vec3 {
+        let mut xyz = Vec3::new(0.0, 0.0, 0.0);
+        for i in 0..N_WAVELENGTHS {
+            let pdf = wavelengths.pdf[i];
+            if pdf == 0.0 {
+                continue;
+            }
+            let lambda = wavelengths.lambda[i];
+            let weight = self.values[i] / pdf;
+            xyz = xyz + Vec3::new(cie_x(lambda), cie_y(lambda), cie_z(lambda)) * weight;
+        }
+
+        xyz / (N_WAVELENGTHS as f64 * CIE_Y_INTEGRAL)
+    }
+}
+
+// arithmetic shared by rgb colors and sampled spectra, for materials that
+// implement their scattering once for both
+pub trait Color:
+    Copy
+    + ops::Add<Output = Self>
+    + ops::Sub<Output = Self>
+    + ops::Mul<Output = Self>
+    + ops::Mul<f64, Output = Self>
+{
+    fn splat(value: f64) -> Self;
+
+    fn average(&self) -> f64;
+
+    fn is_black(&self) -> bool;
+}
+
+impl Color for Vec3 {
+    fn splat(value: f64) -> Vec3 {
+        Vec3::new(value, value, value)
+    }
+
+    fn average(&self) -> f64 {
+        (self.x + self.y + self.z) / 3.0
+    }
+
+    fn is_black(&self) -> bool {
+        self.near_zero()
+    }
+}
+
+impl Color for SampledSpectrum {
+    fn splat(value: f64) -> SampledSpectrum {
+        SampledSpectrum::new(value)
+    }
+
+    fn average(&self) -> f64 {
+        self.values.iter().sum::<f64>() / N_WAVELENGTHS as f64
+    }
+
+    fn is_black(&self) -> bool {
+        SampledSpectrum::is_black(self)
+    }
+}
+
+// wavelength dependent index of refraction, wavelengths in nanometers
+#[derive(Copy, Clone, Debug)]
+pub enum Ior {
+    Constant(f64),
+    // n = a + b / lambda^2, lambda in micrometers
+    Cauchy { a: f64, b: f64 },
+    // n^2 = 1 + sum b_i lambda^2 / (lambda^2 - c_i), lambda in micrometers
+    Sellmeier { b: [f64; 3], c: [f64; 3] },
+}
+
+// wavelength of the sodium d line, where catalogs quote a single index
+pub const LAMBDA_D: f64 = 587.6;
+
+impl Ior {
+    pub fn at(&self, lambda: f64) -> f64 {
+        let um = lambda / 1000.0;
+        let um2 = um * um;
+        match *self {
+            Ior::Constant(n) => n,
+            Ior::Cauchy { a, b } => a + b / um2,
+            Ior::Sellmeier { b, c } => {
+                let mut n2 = 1.0;
+                for i in 0..3 {
+                    n2 += b[i] * um2 / (um2 - c[i]);
+                }
+                n2.sqrt()
+            }
+        }
+    }
+
+    pub fn is_dispersive(&self) -> bool {
+        !matches!(self, Ior::Constant(_))
+    }
+
+    // schott n-bk7 crown glass
+    pub fn bk7() -> Ior {
+        Ior::Sellmeier {
+            b: [1.03961212, 0.231792344, 1.01046945],
+            c: [0.00600069867, 0.0200179144, 103.560653],
+        }
+    }
+
+    // schott sf11 dense flint glass, strongly dispersive
+    pub fn sf11() -> Ior {
+        Ior::Sellmeier {
+            b: [1.73759695, 0.313747346, 1.89878101],
+            c: [0.013188707, 0.0623068142, 155.23629],
+        }
+    }
+
+    pub fn fused_silica() -> Ior {
+        Ior::Sellmeier {
+            b: [0.6961663, 0.4079426, 0.8974794],
+            c: [0.00467914826, 0.0135120631, 97.9340025],
+        }
+    }
+
+    pub fn diamond() -> Ior {
+        Ior::Sellmeier {
+            b: [0.3306, 4.3356, 0.0],
+            c: [0.030625, 0.011236, 0.0],
+        }
+    }
+}
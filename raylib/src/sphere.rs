@@ -1,5 +1,6 @@
 use crate::hittable::{Hit, Hittable};
 use crate::material::Material;
+use crate::random_unit_vector;
 use crate::ray::Ray;
 use crate::vec::Vec3;
 
@@ -17,6 +18,33 @@ impl Sphere {
             material: material,
         }
     }
+
+    // u goes around the y axis starting at -x, v from the bottom pole to the top
+    fn uv(outward_normal: Vec3) -> (f64, f64) {
+        let theta = (-outward_normal.y).acos();
+        let phi = (-outward_normal.z).atan2(outward_normal.x) + std::f64::consts::PI;
+
+        (
+            phi / (2.0 * std::f64::consts::PI),
+            theta / std::f64::consts::PI,
+        )
+    }
+
+    // derivatives of the point along the coordinates from uv, zero at the poles
+    fn partials(&self, outward_normal: Vec3) -> (Vec3, Vec3) {
+        let n = outward_normal;
+        let sin_theta = (n.x * n.x + n.z * n.z).sqrt();
+        if sin_theta < 1e-8 {
+            return (Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0));
+        }
+        let pi = std::f64::consts::PI;
+
+        (
+            Vec3::new(n.z, 0.0, -n.x) * (2.0 * pi * self.radius),
+            Vec3::new(-n.x * n.y / sin_theta, sin_theta, -n.y * n.z / sin_theta)
+                * (pi * self.radius),
+        )
+    }
 }
 
 impl Hittable for Sphere {
@@ -51,12 +79,43 @@ impl Hittable for Sphere {
         }
 
         // https://doc.rust-lang.org/std/option/enum.Option.html#method.as_deref
-        return Some(Hit::new(
-            root,
-            point,
-            normal,
-            front_face,
-            self.material.as_deref(),
-        ));
+        let mut hit = Hit::new(root, point, normal, front_face, self.material.as_deref());
+        let (u, v) = Sphere::uv(outward_normal);
+        hit.u = u;
+        hit.v = v;
+        let (dpdu, dpdv) = self.partials(outward_normal);
+        hit.dpdu = dpdu;
+        hit.dpdv = dpdv;
+
+        return Some(hit);
+    }
+
+    fn sample(&self, rng: &mut rand::rngs::ThreadRng) -> Option<Hit<'_>> {
+        let outward_normal = random_unit_vector(rng);
+        let point = self.center + outward_normal * self.radius;
+
+        let mut hit = Hit::new(0.0, point, outward_normal, true, self.material.as_deref());
+        let (u, v) = Sphere::uv(outward_normal);
+        hit.u = u;
+        hit.v = v;
+        let (dpdu, dpdv) = self.partials(outward_normal);
+        hit.dpdu = dpdu;
+        hit.dpdv = dpdv;
+
+        Some(hit)
+    }
+
+    fn area(&self) -> f64 {
+        4.0 * std::f64::consts::PI * self.radius * self.radius
+    }
+
+    fn bounding_box(&self) -> Option<(Vec3, Vec3)> {
+        let radius = self.radius.abs();
+        let extent = Vec3::new(radius, radius, radius);
+        Some((self.center - extent, self.center + extent))
+    }
+
+    fn material(&self) -> Option<&dyn Material> {
+        self.material.as_deref()
     }
 }
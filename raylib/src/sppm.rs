@@ -0,0 +1,393 @@
+use crate::background::Background;
+use crate::camera::Camera;
+use crate::hittable::{Hit, Hittables};
+use crate::image::Image;
+use crate::integrator::{next_surface, occluded, SURFACE_EPSILON};
+use crate::lights::Lights;
+use crate::material::Material;
+use crate::onb::Onb;
+use crate::photon_map::{Photon, PhotonMap};
+use crate::ray::Ray;
+use crate::render::RenderSettings;
+use crate::vec::Vec3;
+use crate::{random, random_in_unit_disk};
+
+use std::f64::consts::PI;
+
+// share of the new photons a pixel keeps when its radius shrinks, 2/3 as
+// suggested by hachisuka and jensen
+const ALPHA: f64 = 2.0 / 3.0;
+
+// running estimate of one pixel
+struct Pixel {
+    // light reaching the camera directly or off the visible point's lights
+    direct: Vec3,
+    // photon flux gathered within the radius so far
+    flux: Vec3,
+    photons: f64,
+    radius: f64,
+}
+
+// stochastic progressive photon mapping (hachisuka and jensen, 2009). every pass
+// shoots photons from the lights into a kd-tree and traces one camera ray per
+// pixel through specular surfaces to the first diffuse one, where the photons
+// around it are gathered. each pixel's gather radius shrinks as photons come in,
+// so the blur of the density estimate goes away over passes. direct light is
+// sampled at the visible point instead of gathered. media are ignored, and
+// photons only leave the background when every shape has a bounding box.
+// photons and camera rays pick their moments in the shutter independently, so
+// light on moving objects is blurred a little more than it should be
+pub struct PhotonMapping<'world> {
+    camera: &'world Camera,
+    world: &'world Hittables,
+    background: &'world dyn Background,
+    lights: Lights<'world>,
+    // sphere around the world that background photons start from
+    bounds: Option<(Vec3, f64)>,
+    max_depth: i32,
+}
+
+impl<'world> PhotonMapping<'world> {
+    pub fn new(
+        camera: &'world Camera,
+        world: &'world Hittables,
+        background: &'world dyn Background,
+        settings: &RenderSettings,
+        rng: &mut rand::rngs::ThreadRng,
+    ) -> PhotonMapping<'world> {
+        let boxes: Option<std::vec::Vec<(Vec3, Vec3)>> = world
+            .list
+            .iter()
+            .map(|object| object.bounding_box())
+            .collect();
+        let bounds = boxes.filter(|boxes| !boxes.is_empty()).map(|boxes| {
+            let (mut min, mut max) = boxes[0];
+            for (b_min, b_max) in boxes.iter() {
+                min = Vec3::new(min.x.min(b_min.x), min.y.min(b_min.y), min.z.min(b_min.z));
+                max = Vec3::new(max.x.max(b_max.x), max.y.max(b_max.y), max.z.max(b_max.z));
+            }
+            let center = (min + max) * 0.5;
+            (center, (max - center).length())
+        });
+
+        PhotonMapping {
+            camera,
+            world,
+            background,
+            lights: Lights::new(world, rng),
+            bounds,
+            max_depth: settings.max_depth,
+        }
+    }
+
+    // one pass per sample per pixel, each shooting photons_per_pass photons and
+    // starting from pixels gathering within radius, in world units
+    pub fn render(
+        &self,
+        settings: &RenderSettings,
+        photons_per_pass: usize,
+        radius: f64,
+        rng: &mut rand::rngs::ThreadRng,
+    ) -> Image {
+        let width = settings.width;
+        let height = settings.height;
+        let (span_x, span_y) = settings.film_span();
+        let mut pixels: std::vec::Vec<Pixel> = (0..width * height)
+            .map(|_| Pixel {
+                direct: Vec3::new(0.0, 0.0, 0.0),
+                flux: Vec3::new(0.0, 0.0, 0.0),
+                photons: 0.0,
+                radius,
+            })
+            .collect();
+
+        let passes = settings.samples_per_pixel.max(1);
+        let mut sampler = settings.sampler.create(passes as usize, rng);
+        for pass in 0..passes {
+            let mut photons = std::vec::Vec::new();
+            for _ in 0..photons_per_pass {
+                self.shoot(&mut photons, rng);
+            }
+            let map = PhotonMap::new(photons);
+
+            for y in 0..height {
+                let h = height - 1 - y;
+                for x in 0..width {
+                    sampler.start_pixel_sample(x, y, pass as usize);
+                    let (jitter_x, jitter_y) = sampler.get_2d(rng);
+                    let u = (x as f64 + jitter_x) / span_x;
+                    let v = (h as f64 + jitter_y) / span_y;
+                    let lens = sampler.get_2d(rng);
+                    let time = sampler.get_1d(rng);
+                    if let Some(ray) = self.camera.generate_ray(u, v, lens, time, rng) {
+                        self.gather(&ray, &map, &mut pixels[y * width + x], rng);
+                    }
+                }
+            }
+        }
+
+        let mut image = Image::new(width, height);
+        let photons = passes as f64 * photons_per_pass as f64;
+        for y in 0..height {
+            for x in 0..width {
+                let pixel = &pixels[y * width + x];
+                let indirect = pixel.flux / (photons * PI * pixel.radius * pixel.radius);
+                image.set(x, y, pixel.direct / passes as f64 + indirect);
+            }
+        }
+        image
+    }
+
+    // a ray leaving a light and the power it carries
+    fn emit(&self, rng: &mut rand::rngs::ThreadRng) -> Option<(Ray, Vec3)> {
+        let time = self.camera.sample_time(rng);
+        match self.lights.pick(rng) {
+            Some(light) => {
+                let hit = light.object.sample(rng)?;
+                let emitted = light.material.emitted(&hit);
+                let pdf = self.lights.pdf_position();
+                if emitted.near_zero() || pdf <= 0.0 {
+                    return None;
+                }
+
+                // cosine weighted, which cancels the emission's cosine but for pi
+                let onb = Onb::from_w(hit.geometric_normal);
+                let r1 = random(rng);
+                let r2 = random(rng);
+                let phi = 2.0 * PI * r1;
+                let direction = onb.local(
+                    phi.cos() * r2.sqrt(),
+                    phi.sin() * r2.sqrt(),
+                    (1.0 - r2).sqrt(),
+                );
+                Some((
+                    Ray::with_time(hit.point, direction, time),
+                    emitted * (PI / pdf),
+                ))
+            }
+            None => {
+                // from a disk facing the world just outside its bounding sphere
+                let (center, radius) = self.bounds?;
+                let (direction, radiance, pdf) =
+                    self.background.sample((random(rng), random(rng)))?;
+                if pdf <= 0.0 {
+                    return None;
+                }
+                let onb = Onb::from_w(direction);
+                let disk = random_in_unit_disk(rng) * radius;
+                let origin = center + onb.local(disk.x, disk.y, radius);
+
+                let pdf = pdf * self.lights.background_probability() / (PI * radius * radius);
+                Some((Ray::with_time(origin, -direction, time), radiance / pdf))
+            }
+        }
+    }
+
+    // traces one photon, leaving it on every diffuse surface after the first it
+    // meets, whose light the camera side samples directly
+    fn shoot(&self, photons: &mut std::vec::Vec<Photon>, rng: &mut rand::rngs::ThreadRng) {
+        let (mut ray, mut power) = match self.emit(rng) {
+            Some(emitted) => emitted,
+            None => return,
+        };
+
+        for depth in 0..self.max_depth {
+            let hit = match next_surface(self.world, &ray, rng) {
+                Some(hit) => hit,
+                None => return,
+            };
+            let material = match hit.material {
+                Some(material) => material,
+                None => panic!("expecting material"),
+            };
+
+            if depth > 0 && diffuse(material, &ray, &hit) {
+                photons.push(Photon {
+                    point: hit.point,
+                    direction: -ray.direction.unit(),
+                    power,
+                });
+            }
+
+            let (scattered, attenuation, _) = match material.scatter_with_pdf(&ray, hit, rng) {
+                Some(scattered) => scattered,
+                None => return,
+            };
+
+            // russian roulette on how much of the power the bounce keeps
+            let scattered_power = power * attenuation;
+            let keep = match power.luminance() {
+                l if l > 0.0 => (scattered_power.luminance() / l).min(1.0),
+                _ => 0.0,
+            };
+            if keep <= 0.0 || random(rng) >= keep {
+                return;
+            }
+            power = scattered_power / keep;
+            ray = Ray::with_time(scattered.origin, scattered.direction.unit(), ray.time);
+        }
+    }
+
+    // follows a camera ray to its visible point and adds what it sees to the pixel
+    fn gather(
+        &self,
+        ray: &Ray,
+        map: &PhotonMap,
+        pixel: &mut Pixel,
+        rng: &mut rand::rngs::ThreadRng,
+    ) {
+        let mut ray = Ray::with_time(ray.origin, ray.direction.unit(), ray.time);
+        let mut beta = Vec3::new(1.0, 1.0, 1.0);
+
+        for _ in 0..self.max_depth {
+            let hit = match next_surface(self.world, &ray, rng) {
+                Some(hit) => hit,
+                None => {
+                    pixel.direct = pixel.direct + beta * self.background.color(ray.direction);
+                    return;
+                }
+            };
+            let material = match hit.material {
+                Some(material) => material,
+                None => panic!("expecting material"),
+            };
+            pixel.direct = pixel.direct + beta * material.emitted(&hit);
+
+            if diffuse(material, &ray, &hit) {
+                pixel.direct = pixel.direct + beta * self.direct_light(&ray, &hit, material, rng);
+
+                let mut flux = Vec3::new(0.0, 0.0, 0.0);
+                let mut count = 0.0;
+                map.gather(hit.point, pixel.radius, &mut |photon: &Photon| {
+                    let cos_theta = photon.direction.dot(&hit.normal).abs();
+                    if cos_theta < 1e-6 {
+                        return;
+                    }
+                    if let Some((f, _)) = material.eval(&ray, &hit, photon.direction) {
+                        flux = flux + f / cos_theta * photon.power;
+                        count += 1.0;
+                    }
+                });
+
+                // keep a share of the new photons and shrink the radius to match,
+                // scaling down the flux gathered over the larger disk
+                if count > 0.0 {
+                    let photons = pixel.photons + ALPHA * count;
+                    let radius = pixel.radius * (photons / (pixel.photons + count)).sqrt();
+                    let shrink = (radius / pixel.radius) * (radius / pixel.radius);
+                    pixel.flux = (pixel.flux + beta * flux) * shrink;
+                    pixel.photons = photons;
+                    pixel.radius = radius;
+                }
+                return;
+            }
+
+            match material.scatter_with_pdf(&ray, hit, rng) {
+                Some((scattered, attenuation, _)) => {
+                    beta = beta * attenuation;
+                    ray = Ray::with_time(scattered.origin, scattered.direction.unit(), ray.time);
+                }
+                None => return,
+            }
+        }
+    }
+
+    // light from one light picked at random, reaching the hit directly
+    fn direct_light(
+        &self,
+        ray: &Ray,
+        hit: &Hit,
+        material: &dyn Material,
+        rng: &mut rand::rngs::ThreadRng,
+    ) -> Vec3 {
+        let zero = Vec3::new(0.0, 0.0, 0.0);
+        match self.lights.pick(rng) {
+            Some(light) => {
+                let mut sample = match light.object.sample(rng) {
+                    Some(sample) => sample,
+                    None => return zero,
+                };
+                let to_light = sample.point - hit.point;
+                let distance_squared = to_light.length_squared();
+                let direction = to_light.unit();
+                let cos_light = sample.geometric_normal.dot(&direction);
+                if distance_squared == 0.0 || cos_light == 0.0 {
+                    return zero;
+                }
+                // seen from behind when the outward normal points away
+                sample.front_face = cos_light < 0.0;
+
+                let emitted = light.material.emitted(&sample);
+                let f = match material.eval(ray, hit, direction) {
+                    Some((f, _)) => f,
+                    None => return zero,
+                };
+                if emitted.near_zero() || f.near_zero() {
+                    return zero;
+                }
+                let t_max = 1.0 - SURFACE_EPSILON / distance_squared.sqrt();
+                if occluded(
+                    self.world,
+                    &Ray::with_time(hit.point, to_light, ray.time),
+                    t_max,
+                    rng,
+                ) {
+                    return zero;
+                }
+
+                let pdf = self.lights.pdf_position() * distance_squared / cos_light.abs();
+                f * emitted / pdf
+            }
+            None => {
+                let (direction, radiance, pdf) =
+                    match self.background.sample((random(rng), random(rng))) {
+                        Some(sample) => sample,
+                        None => return zero,
+                    };
+                let f = match material.eval(ray, hit, direction) {
+                    Some((f, _)) => f,
+                    None => return zero,
+                };
+                if pdf <= 0.0 || f.near_zero() {
+                    return zero;
+                }
+                if occluded(
+                    self.world,
+                    &Ray::with_time(hit.point, direction, ray.time),
+                    f64::INFINITY,
+                    rng,
+                ) {
+                    return zero;
+                }
+                f * radiance / (pdf * self.lights.background_probability())
+            }
+        }
+    }
+}
+
+// whether the material has a smooth lobe to evaluate, where photons can be
+// stored and gathered, rather than being purely specular
+fn diffuse(material: &dyn Material, ray: &Ray, hit: &Hit) -> bool {
+    material.eval(ray, hit, hit.normal).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lambertian::Lambertian;
+    use crate::render::{white_furnace, Integrator, RenderSettings};
+    use crate::vec::Vec3;
+
+    #[test]
+    fn white_furnace_keeps_energy() {
+        let mut settings = RenderSettings::new(8, 8, 32, 4);
+        settings.integrator = Integrator::PhotonMapping {
+            photons_per_pass: 5000,
+            radius: 0.2,
+        };
+        let white = Box::new(Lambertian::new(Vec3::new(1.0, 1.0, 1.0)));
+
+        // photon density estimates blur, which costs a little at any radius
+        let mean = white_furnace(white, &settings);
+        assert!((mean - 1.0).abs() < 0.04, "furnace mean {}", mean);
+    }
+}
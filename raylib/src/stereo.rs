@@ -0,0 +1,143 @@
+use crate::background::Background;
+use crate::camera::{Camera, Projection};
+use crate::hittable::Hittables;
+use crate::image::Image;
+use crate::render::{render, RenderSettings};
+use crate::vec::Vec3;
+
+// how the two eyes are aimed at the convergence distance, where objects appear
+// on the screen rather than in front of or behind it
+#[derive(Copy, Clone, Debug)]
+pub enum Convergence {
+    // parallel eyes with their image windows shifted to overlap there. no
+    // vertical parallax, so it's the comfortable choice for viewing
+    OffAxis,
+    // each eye turned to look at the convergence point, like a pair of real
+    // cameras on a rig. the image corners get some vertical parallax
+    ToeIn,
+}
+
+// where the eyes' images go in the combined one
+#[derive(Copy, Clone, Debug)]
+pub enum StereoLayout {
+    // left eye on the left half
+    SideBySide,
+    // left eye on the top half
+    TopBottom,
+}
+
+// a pair of eyes interocular apart, in world units, around where a single camera
+// would be. 0.064 is an average adult's in meters
+#[derive(Copy, Clone, Debug)]
+pub struct Stereo {
+    pub interocular: f64,
+    pub convergence_distance: f64,
+    pub convergence: Convergence,
+}
+
+impl Stereo {
+    pub fn new(interocular: f64, convergence_distance: f64, convergence: Convergence) -> Stereo {
+        Stereo {
+            interocular,
+            convergence_distance,
+            convergence,
+        }
+    }
+
+    // pinhole eyes for a camera at look_from looking at look_at, left first
+    pub fn eyes(
+        &self,
+        look_from: Vec3,
+        look_at: Vec3,
+        view_up: Vec3,
+        vfov: f64,
+        aspect_ratio: f64,
+    ) -> (Camera, Camera) {
+        let forward = (look_at - look_from).unit();
+        let right = forward.cross(&view_up).unit();
+        let half = right * (self.interocular / 2.0);
+        let target = look_from + forward * self.convergence_distance;
+
+        match self.convergence {
+            Convergence::OffAxis => {
+                let eye = |offset: Vec3| {
+                    // the window at the convergence distance stays where the
+                    // center camera has it, with only the eye moving
+                    let mut camera = Camera::new_12_2(
+                        look_from,
+                        target,
+                        view_up,
+                        vfov,
+                        aspect_ratio,
+                        0.0,
+                        self.convergence_distance,
+                    );
+                    camera.origin = camera.origin + offset;
+                    camera
+                };
+                (eye(-half), eye(half))
+            }
+            Convergence::ToeIn => {
+                let eye = |offset: Vec3| {
+                    let origin = look_from + offset;
+                    Camera::new_12_2(
+                        origin,
+                        target,
+                        view_up,
+                        vfov,
+                        aspect_ratio,
+                        0.0,
+                        (target - origin).length(),
+                    )
+                };
+                (eye(-half), eye(half))
+            }
+        }
+    }
+
+    // omni-directional stereo panoramas, equirectangular for each eye. objects
+    // closer than the interocular distance can't be fused. left first
+    pub fn omni(&self, look_from: Vec3, look_at: Vec3, view_up: Vec3) -> (Camera, Camera) {
+        let eye = |offset: f64| {
+            let mut camera = Camera::equirectangular(look_from, look_at, view_up);
+            camera.projection = Projection::OmniStereo { offset };
+            camera
+        };
+        (eye(-self.interocular / 2.0), eye(self.interocular / 2.0))
+    }
+}
+
+// both eyes rendered at the settings' size and put into one image, twice as wide
+// or twice as tall
+pub fn render_stereo(
+    eyes: &(Camera, Camera),
+    world: &Hittables,
+    background: &dyn Background,
+    settings: &RenderSettings,
+    layout: StereoLayout,
+    rng: &mut rand::rngs::ThreadRng,
+) -> Image {
+    let left = render(&eyes.0, world, background, settings, rng);
+    let right = render(&eyes.1, world, background, settings, rng);
+    combine(&left, &right, layout)
+}
+
+pub fn combine(left: &Image, right: &Image, layout: StereoLayout) -> Image {
+    let (width, height) = (left.width, left.height);
+    if right.width != width || right.height != height {
+        panic!("error stereo images differ in size");
+    }
+    let (dx, dy) = match layout {
+        StereoLayout::SideBySide => (width, 0),
+        StereoLayout::TopBottom => (0, height),
+    };
+
+    let mut image = Image::new(width + dx, height + dy);
+    for y in 0..height {
+        for x in 0..width {
+            image.set(x, y, left.get(x, y));
+            image.set(x + dx, y + dy, right.get(x, y));
+        }
+    }
+    image
+}
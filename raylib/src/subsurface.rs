@@ -0,0 +1,53 @@
+use crate::hittable::Hit;
+use crate::material::Material;
+use crate::medium::{Medium, Scattering};
+use crate::ray::Ray;
+use crate::rough_dielectric::RoughDielectric;
+use crate::vec::Vec3;
+
+// translucent material like skin, marble or milk. the surface is a dielectric
+// boundary and the light refracted through it takes a random walk through the
+// scattering medium inside until it leaves again, so the object has to be closed
+pub struct Subsurface {
+    pub surface: RoughDielectric,
+    pub medium: Scattering,
+}
+
+impl Subsurface {
+    // albedo is the color the material ends up with, the mean free path how far
+    // light travels between scattering events, per channel
+    pub fn new(
+        albedo: Vec3,
+        mean_free_path: Vec3,
+        index_of_refraction: f64,
+        roughness: f64,
+    ) -> Subsurface {
+        Subsurface {
+            surface: RoughDielectric::new(index_of_refraction, roughness),
+            medium: Scattering::from_albedo(albedo, mean_free_path, 0.0),
+        }
+    }
+}
+
+impl Material for Subsurface {
+    fn scatter(&self, ray: &Ray, hit: Hit, rng: &mut rand::rngs::ThreadRng) -> Option<(Ray, Vec3)> {
+        self.surface.scatter(ray, hit, rng)
+    }
+
+    fn eval(&self, ray: &Ray, hit: &Hit, direction: Vec3) -> Option<(Vec3, f64)> {
+        self.surface.eval(ray, hit, direction)
+    }
+
+    fn scatter_with_pdf(
+        &self,
+        ray: &Ray,
+        hit: Hit,
+        rng: &mut rand::rngs::ThreadRng,
+    ) -> Option<(Ray, Vec3, Option<f64>)> {
+        self.surface.scatter_with_pdf(ray, hit, rng)
+    }
+
+    fn interior(&self) -> Option<&dyn Medium> {
+        Some(&self.medium)
+    }
+}
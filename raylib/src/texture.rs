@@ -0,0 +1,93 @@
+use crate::hdr::Hdr;
+use crate::image::Image;
+use crate::vec::Vec3;
+
+// a color that varies over a surface, looked up by the hit's surface coordinates
+// or by the point itself
+pub trait Texture {
+    fn value(&self, u: f64, v: f64, point: Vec3) -> Vec3;
+}
+
+pub struct SolidColor {
+    pub color: Vec3,
+}
+
+impl SolidColor {
+    pub fn new(color: Vec3) -> SolidColor {
+        SolidColor { color }
+    }
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _u: f64, _v: f64, _point: Vec3) -> Vec3 {
+        self.color
+    }
+}
+
+// checkerboard of cubes `scale` wide filling space, so it needs no coordinates
+pub struct Checker {
+    pub even: Box<dyn Texture>,
+    pub odd: Box<dyn Texture>,
+    pub scale: f64,
+}
+
+impl Checker {
+    pub fn new(even: Box<dyn Texture>, odd: Box<dyn Texture>, scale: f64) -> Checker {
+        Checker { even, odd, scale }
+    }
+
+    pub fn colors(even: Vec3, odd: Vec3, scale: f64) -> Checker {
+        Checker::new(
+            Box::new(SolidColor::new(even)),
+            Box::new(SolidColor::new(odd)),
+            scale,
+        )
+    }
+}
+
+impl Texture for Checker {
+    fn value(&self, u: f64, v: f64, point: Vec3) -> Vec3 {
+        let x = (point.x / self.scale).floor() as i64;
+        let y = (point.y / self.scale).floor() as i64;
+        let z = (point.z / self.scale).floor() as i64;
+
+        if (x + y + z).rem_euclid(2) == 0 {
+            self.even.value(u, v, point)
+        } else {
+            self.odd.value(u, v, point)
+        }
+    }
+}
+
+// image stretched over the surface coordinates, v = 0 at the bottom row
+pub struct ImageTexture {
+    pub image: Image,
+}
+
+impl ImageTexture {
+    pub fn new(image: Image) -> ImageTexture {
+        ImageTexture { image }
+    }
+
+    pub fn open_hdr(path: &str) -> ImageTexture {
+        let hdr = Hdr::open(path);
+        ImageTexture::new(Image {
+            width: hdr.width,
+            height: hdr.height,
+            pixels: hdr.pixels,
+        })
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f64, v: f64, _point: Vec3) -> Vec3 {
+        if self.image.width == 0 || self.image.height == 0 {
+            return Vec3::new(0.0, 1.0, 1.0);
+        }
+        let x = (u.clamp(0.0, 1.0) * self.image.width as f64) as usize;
+        let y = ((1.0 - v.clamp(0.0, 1.0)) * self.image.height as f64) as usize;
+
+        self.image
+            .get(x.min(self.image.width - 1), y.min(self.image.height - 1))
+    }
+}
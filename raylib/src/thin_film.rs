@@ -0,0 +1,139 @@
+use crate::hittable::Hit;
+use crate::material::Material;
+use crate::random;
+use crate::ray::Ray;
+use crate::spectrum::{cie_x, cie_y, cie_z, d65, Color, SampledSpectrum, Wavelengths};
+use crate::vec::Vec3;
+use crate::xyz_to_rgb;
+
+// a film a few hundred nanometers thick whose reflections interfere, giving the
+// colors of soap bubbles and oil slicks
+#[derive(Copy, Clone, Debug)]
+pub struct ThinFilm {
+    // in nanometers
+    pub thickness: f64,
+    pub index_of_refraction: f64,
+}
+
+impl ThinFilm {
+    pub fn new(thickness: f64, index_of_refraction: f64) -> ThinFilm {
+        ThinFilm {
+            thickness,
+            index_of_refraction,
+        }
+    }
+
+    // airy summation of the light bouncing inside a film lying on a substrate of
+    // index `base_ior`, for light arriving from air at `cos_theta`
+    pub fn reflectance(&self, cos_theta: f64, lambda: f64, base_ior: f64) -> f64 {
+        let n0 = 1.0;
+        let n1 = self.index_of_refraction;
+        let n2 = base_ior;
+
+        let cos0 = cos_theta.abs().min(1.0);
+        let sin2_0 = 1.0 - cos0 * cos0;
+        let cos1 = (1.0 - sin2_0 / (n1 * n1)).max(0.0).sqrt();
+        let sin2_2 = sin2_0 / (n2 * n2);
+        if sin2_2 >= 1.0 {
+            return 1.0;
+        }
+        let cos2 = (1.0 - sin2_2).sqrt();
+
+        let phase = 4.0 * std::f64::consts::PI * n1 * self.thickness * cos1 / lambda;
+        let airy = |r01: f64, r12: f64| {
+            let cross = 2.0 * r01 * r12 * phase.cos();
+            (r01 * r01 + r12 * r12 + cross) / (1.0 + r01 * r01 * r12 * r12 + cross)
+        };
+
+        let r01_s = (n0 * cos0 - n1 * cos1) / (n0 * cos0 + n1 * cos1);
+        let r12_s = (n1 * cos1 - n2 * cos2) / (n1 * cos1 + n2 * cos2);
+        let r01_p = (n1 * cos0 - n0 * cos1) / (n1 * cos0 + n0 * cos1);
+        let r12_p = (n2 * cos1 - n1 * cos2) / (n2 * cos1 + n1 * cos2);
+
+        0.5 * (airy(r01_s, r12_s) + airy(r01_p, r12_p))
+    }
+
+    // reflectance integrated over the visible spectrum under d65, as linear rgb
+    pub fn reflectance_rgb(&self, cos_theta: f64, base_ior: f64) -> Vec3 {
+        let mut xyz = Vec3::new(0.0, 0.0, 0.0);
+        let mut white = 0.0;
+        let mut lambda = 380.0;
+        while lambda <= 780.0 {
+            let light = d65(lambda);
+            let r = self.reflectance(cos_theta, lambda, base_ior) * light;
+            xyz = xyz + Vec3::new(cie_x(lambda), cie_y(lambda), cie_z(lambda)) * r;
+            white += cie_y(lambda) * light;
+            lambda += 10.0;
+        }
+        let rgb = xyz_to_rgb(xyz / white);
+
+        Vec3::new(rgb.x.max(0.0), rgb.y.max(0.0), rgb.z.max(0.0))
+    }
+
+    pub fn reflectance_spectral(
+        &self,
+        cos_theta: f64,
+        base_ior: f64,
+        wavelengths: &Wavelengths,
+    ) -> SampledSpectrum {
+        SampledSpectrum::from_fn(wavelengths, |lambda| {
+            self.reflectance(cos_theta, lambda, base_ior)
+        })
+    }
+}
+
+// soap bubble: a film with air on both sides. transmitted light keeps its direction
+#[derive(Clone)]
+pub struct Bubble {
+    pub film: ThinFilm,
+}
+
+impl Bubble {
+    pub fn new(thickness: f64, index_of_refraction: f64) -> Bubble {
+        Bubble {
+            film: ThinFilm::new(thickness, index_of_refraction),
+        }
+    }
+
+    fn reflect_or_transmit<C: Color>(
+        ray: &Ray,
+        hit: &Hit,
+        reflectance: C,
+        rng: &mut rand::rngs::ThreadRng,
+    ) -> (Ray, C) {
+        let p = reflectance.average().clamp(1e-3, 1.0 - 1e-3);
+        if random(rng) < p {
+            let direction = ray.direction.unit().reflect(hit.normal);
+            (Ray::new(hit.point, direction), reflectance * (1.0 / p))
+        } else {
+            let transmittance = C::splat(1.0) - reflectance;
+            (
+                Ray::new(hit.point, ray.direction),
+                transmittance * (1.0 / (1.0 - p)),
+            )
+        }
+    }
+}
+
+impl Material for Bubble {
+    fn scatter(&self, ray: &Ray, hit: Hit, rng: &mut rand::rngs::ThreadRng) -> Option<(Ray, Vec3)> {
+        let cos_theta = ray.direction.unit().dot(&hit.normal);
+        let reflectance = self.film.reflectance_rgb(cos_theta, 1.0);
+
+        Some(Bubble::reflect_or_transmit(ray, &hit, reflectance, rng))
+    }
+
+    fn scatter_spectral(
+        &self,
+        ray: &Ray,
+        hit: Hit,
+        wavelengths: &mut Wavelengths,
+        rng: &mut rand::rngs::ThreadRng,
+    ) -> Option<(Ray, SampledSpectrum, Option<f64>)> {
+        let cos_theta = ray.direction.unit().dot(&hit.normal);
+        let reflectance = self.film.reflectance_spectral(cos_theta, 1.0, wavelengths);
+        let (scattered, attenuation) = Bubble::reflect_or_transmit(ray, &hit, reflectance, rng);
+
+        Some((scattered, attenuation, None))
+    }
+}
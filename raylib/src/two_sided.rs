@@ -0,0 +1,128 @@
+use crate::hittable::Hit;
+use crate::material::Material;
+use crate::medium::Medium;
+use crate::ray::Ray;
+use crate::spectrum::{SampledSpectrum, Wavelengths};
+use crate::vec::Vec3;
+
+// thin surface such as a leaf or a sheet of paper, with no inside. a hit from
+// behind is shaded as a front face hit, by the back material if there is one
+pub struct TwoSided {
+    pub front: Box<dyn Material>,
+    pub back: Option<Box<dyn Material>>,
+}
+
+impl TwoSided {
+    pub fn new(material: Box<dyn Material>) -> TwoSided {
+        TwoSided {
+            front: material,
+            back: None,
+        }
+    }
+
+    pub fn with_back(front: Box<dyn Material>, back: Box<dyn Material>) -> TwoSided {
+        TwoSided {
+            front,
+            back: Some(back),
+        }
+    }
+
+    fn side<'world>(&self, hit: &Hit<'world>) -> (&dyn Material, Hit<'world>) {
+        if hit.front_face {
+            return (self.front.as_ref(), *hit);
+        }
+        let material = self.back.as_deref().unwrap_or_else(|| self.front.as_ref());
+
+        (
+            material,
+            Hit {
+                front_face: true,
+                ..*hit
+            },
+        )
+    }
+}
+
+impl Material for TwoSided {
+    fn scatter(&self, ray: &Ray, hit: Hit, rng: &mut rand::rngs::ThreadRng) -> Option<(Ray, Vec3)> {
+        let (material, hit) = self.side(&hit);
+        material.scatter(ray, hit, rng)
+    }
+
+    fn eval(&self, ray: &Ray, hit: &Hit, direction: Vec3) -> Option<(Vec3, f64)> {
+        let (material, hit) = self.side(hit);
+        material.eval(ray, &hit, direction)
+    }
+
+    fn scatter_with_pdf(
+        &self,
+        ray: &Ray,
+        hit: Hit,
+        rng: &mut rand::rngs::ThreadRng,
+    ) -> Option<(Ray, Vec3, Option<f64>)> {
+        let (material, hit) = self.side(&hit);
+        material.scatter_with_pdf(ray, hit, rng)
+    }
+
+    fn scatter_sampled(
+        &self,
+        ray: &Ray,
+        hit: Hit,
+        u_lobe: f64,
+        u: (f64, f64),
+        rng: &mut rand::rngs::ThreadRng,
+    ) -> Option<(Ray, Vec3, Option<f64>)> {
+        let (material, hit) = self.side(&hit);
+        material.scatter_sampled(ray, hit, u_lobe, u, rng)
+    }
+
+    fn scatter_spectral(
+        &self,
+        ray: &Ray,
+        hit: Hit,
+        wavelengths: &mut Wavelengths,
+        rng: &mut rand::rngs::ThreadRng,
+    ) -> Option<(Ray, SampledSpectrum, Option<f64>)> {
+        let (material, hit) = self.side(&hit);
+        material.scatter_spectral(ray, hit, wavelengths, rng)
+    }
+
+    fn scatter_spectral_sampled(
+        &self,
+        ray: &Ray,
+        hit: Hit,
+        wavelengths: &mut Wavelengths,
+        u_lobe: f64,
+        u: (f64, f64),
+        rng: &mut rand::rngs::ThreadRng,
+    ) -> Option<(Ray, SampledSpectrum, Option<f64>)> {
+        let (material, hit) = self.side(&hit);
+        material.scatter_spectral_sampled(ray, hit, wavelengths, u_lobe, u, rng)
+    }
+
+    fn eval_spectral(
+        &self,
+        ray: &Ray,
+        hit: &Hit,
+        direction: Vec3,
+        wavelengths: &Wavelengths,
+    ) -> Option<(SampledSpectrum, f64)> {
+        let (material, hit) = self.side(hit);
+        material.eval_spectral(ray, &hit, direction, wavelengths)
+    }
+
+    // a light glows on both sides, or as the back material where there is one
+    fn emitted(&self, hit: &Hit) -> Vec3 {
+        let (material, hit) = self.side(hit);
+        material.emitted(&hit)
+    }
+
+    fn interior(&self) -> Option<&dyn Medium> {
+        self.front.interior()
+    }
+
+    fn alpha(&self, hit: &Hit) -> f64 {
+        let (material, hit) = self.side(hit);
+        material.alpha(&hit)
+    }
+}
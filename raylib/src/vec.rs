@@ -117,6 +117,11 @@ impl Vec3 {
     pub fn unit(self) -> Vec3 {
         self / self.length()
     }
+
+    // relative luminance of a linear rgb color
+    pub fn luminance(&self) -> f64 {
+        0.2126 * self.x + 0.7152 * self.y + 0.0722 * self.z
+    }
 }
 
 impl std::fmt::Display for Vec3 {
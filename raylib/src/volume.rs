@@ -0,0 +1,36 @@
+use crate::hittable::Hit;
+use crate::material::Material;
+use crate::medium::Medium;
+use crate::ray::Ray;
+use crate::vec::Vec3;
+
+// invisible boundary around a medium, like the box around a cloud. the surface
+// itself doesn't scatter, it only tells the integrator where the medium is
+pub struct Volume {
+    pub medium: Box<dyn Medium>,
+}
+
+impl Volume {
+    pub fn new(medium: Box<dyn Medium>) -> Volume {
+        Volume { medium }
+    }
+}
+
+impl Material for Volume {
+    fn scatter(
+        &self,
+        ray: &Ray,
+        hit: Hit,
+        _rng: &mut rand::rngs::ThreadRng,
+    ) -> Option<(Ray, Vec3)> {
+        Some((Ray::new(hit.point, ray.direction), Vec3::new(1.0, 1.0, 1.0)))
+    }
+
+    fn interior(&self) -> Option<&dyn Medium> {
+        Some(self.medium.as_ref())
+    }
+
+    fn passes_through(&self) -> bool {
+        true
+    }
+}
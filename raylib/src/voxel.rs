@@ -0,0 +1,121 @@
+use crate::vec::Vec3;
+
+use std::fs;
+use std::path::Path;
+
+// scalar values on a regular grid of cells filling the unit cube, such as the
+// density or temperature of a smoke simulation. x varies fastest in `values`
+pub struct VoxelGrid {
+    pub nx: usize,
+    pub ny: usize,
+    pub nz: usize,
+    pub values: std::vec::Vec<f64>,
+    pub max: f64,
+}
+
+impl VoxelGrid {
+    pub fn new(nx: usize, ny: usize, nz: usize, values: std::vec::Vec<f64>) -> VoxelGrid {
+        if nx == 0 || ny == 0 || nz == 0 {
+            panic!("error voxel grid of {} x {} x {} is empty", nx, ny, nz);
+        }
+        if values.len() != nx * ny * nz {
+            panic!("error voxel grid expects {} values", nx * ny * nz);
+        }
+        let max = values.iter().cloned().fold(0.0, f64::max);
+
+        VoxelGrid {
+            nx,
+            ny,
+            nz,
+            values,
+            max,
+        }
+    }
+
+    pub fn from_fn(nx: usize, ny: usize, nz: usize, f: impl Fn(Vec3) -> f64) -> VoxelGrid {
+        let mut values = std::vec::Vec::with_capacity(nx * ny * nz);
+        for z in 0..nz {
+            for y in 0..ny {
+                for x in 0..nx {
+                    values.push(f(Vec3::new(
+                        (x as f64 + 0.5) / nx as f64,
+                        (y as f64 + 0.5) / ny as f64,
+                        (z as f64 + 0.5) / nz as f64,
+                    )));
+                }
+            }
+        }
+        VoxelGrid::new(nx, ny, nz, values)
+    }
+
+    // a text header line "voxels <nx> <ny> <nz>" followed by the values as little
+    // endian 32 bit floats, the way most simulation tools can dump a raw grid
+    pub fn open(path: &str) -> VoxelGrid {
+        let bytes = match fs::read(Path::new(path)) {
+            Err(err) => panic!("error {}", err),
+            Ok(bytes) => bytes,
+        };
+        let end = match bytes.iter().position(|b| *b == b'\n') {
+            Some(end) => end,
+            None => panic!("error missing voxel header"),
+        };
+
+        let header = String::from_utf8_lossy(&bytes[..end]);
+        let fields: std::vec::Vec<&str> = header.split_whitespace().collect();
+        if fields.len() != 4 || fields[0] != "voxels" {
+            panic!("error bad voxel header {}", header);
+        }
+        let size = |field: &str| match field.parse::<usize>() {
+            Ok(size) => size,
+            Err(err) => panic!("error {}", err),
+        };
+        let (nx, ny, nz) = (size(fields[1]), size(fields[2]), size(fields[3]));
+
+        let data = &bytes[end + 1..];
+        if data.len() != nx * ny * nz * 4 {
+            panic!("error voxel file has {} bytes of data", data.len());
+        }
+        let values = data
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64)
+            .collect();
+
+        VoxelGrid::new(nx, ny, nz, values)
+    }
+
+    fn get(&self, x: usize, y: usize, z: usize) -> f64 {
+        self.values[(z * self.ny + y) * self.nx + x]
+    }
+
+    // trilinear interpolation between cell centers, zero outside the unit cube
+    pub fn lookup(&self, point: Vec3) -> f64 {
+        if point.x < 0.0
+            || point.y < 0.0
+            || point.z < 0.0
+            || point.x > 1.0
+            || point.y > 1.0
+            || point.z > 1.0
+        {
+            return 0.0;
+        }
+        let axis = |p: f64, n: usize| {
+            let p = (p * n as f64 - 0.5).clamp(0.0, (n - 1) as f64);
+            let i = (p.floor() as usize).min(n.saturating_sub(2));
+            (i, (i + 1).min(n - 1), p - i as f64)
+        };
+        let (x0, x1, fx) = axis(point.x, self.nx);
+        let (y0, y1, fy) = axis(point.y, self.ny);
+        let (z0, z1, fz) = axis(point.z, self.nz);
+
+        let lerp = |a: f64, b: f64, t: f64| a * (1.0 - t) + b * t;
+        let plane = |z: usize| {
+            lerp(
+                lerp(self.get(x0, y0, z), self.get(x1, y0, z), fx),
+                lerp(self.get(x0, y1, z), self.get(x1, y1, z), fx),
+                fy,
+            )
+        };
+
+        lerp(plane(z0), plane(z1), fz)
+    }
+}